@@ -1,7 +1,15 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    io::{BufRead as _, Write as _},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Context;
-use reqwest::cookie::{CookieStore, Jar};
+use reqwest::{
+    cookie::{CookieStore, Jar},
+    header::HeaderMap,
+};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 
@@ -12,26 +20,26 @@ pub(crate) struct Auth<'config> {
     bearer_token: &'config SecretString,
     base: &'config url::Url,
     client: reqwest::Client,
+    headers: HeaderMap,
     guest_token: Option<SecretString>,
     csrf: Option<SecretString>,
 }
 
 impl<'config> Auth<'config> {
-    fn new(config: &'config Config) -> Self {
+    fn new(config: &'config Config) -> anyhow::Result<Self> {
         let jar = Arc::new(Jar::default());
 
-        Self {
-            jar: jar.clone(),
+        Ok(Self {
+            client: client_builder(config)?
+                .cookie_provider(jar.clone())
+                .build()?,
+            headers: config.headers.api_headers()?,
+            jar,
             base: &config.base,
             bearer_token: &config.bearer_token,
-            client: reqwest::Client::builder()
-                .timeout(Duration::from_secs(30))
-                .cookie_provider(jar)
-                .build()
-                .unwrap(),
             guest_token: None,
             csrf: None,
-        }
+        })
     }
 
     fn build_flow_request<T: Serialize>(
@@ -42,6 +50,7 @@ impl<'config> Auth<'config> {
         let mut req = self
             .client
             .post(endpoint)
+            .headers(self.headers.clone())
             .header(
                 "x-guest-token",
                 self.guest_token.as_ref().unwrap().expose_secret(),
@@ -92,14 +101,8 @@ impl<'config> Auth<'config> {
             self.base,
         );
 
-        if let Some(cookie) = self.jar.cookies(self.base) {
-            if let Some(string) = cookie
-                .to_str()
-                .ok()
-                .and_then(|s| s.split(';').find_map(|s| s.trim().strip_prefix("ct0=")))
-            {
-                self.csrf = Some(string.into());
-            }
+        if let Some(csrf) = csrf_from_jar(&self.jar, self.base) {
+            self.csrf = Some(csrf);
         }
 
         Ok(response.json().await?)
@@ -109,6 +112,7 @@ impl<'config> Auth<'config> {
         let response = self
             .client
             .post("https://api.twitter.com/1.1/guest/activate.json")
+            .headers(self.headers.clone())
             .bearer_auth(self.bearer_token.expose_secret())
             .send()
             .await?;
@@ -125,15 +129,13 @@ impl<'config> Auth<'config> {
         Ok(())
     }
 
-    async fn login(
-        mut self,
-        config: &AuthConfig,
-    ) -> anyhow::Result<(reqwest::Client, SecretString)> {
+    async fn login(mut self, config: &AuthConfig) -> anyhow::Result<Session> {
         match config {
             AuthConfig::User {
                 username,
                 password,
                 email,
+                ..
             } => {
                 self.fetch_guest_token().await?;
 
@@ -216,7 +218,9 @@ impl<'config> Auth<'config> {
                     }?;
                 }
             }
-            AuthConfig::Cookie { cookie: cookies } => {
+            AuthConfig::Cookie {
+                cookie: cookies, ..
+            } => {
                 for cookie in cookies.expose_secret().split(';') {
                     if let Some(raw) = cookie.trim().strip_prefix("ct0=") {
                         self.csrf = Some(raw.into());
@@ -235,14 +239,127 @@ impl<'config> Auth<'config> {
             }
         }
 
-        Ok((self.client, self.csrf.unwrap()))
+        Ok(Session {
+            client: self.client,
+            jar: self.jar,
+            csrf: self.csrf.unwrap(),
+        })
+    }
+}
+
+pub(crate) struct Session {
+    pub(crate) client: reqwest::Client,
+    pub(crate) jar: Arc<Jar>,
+    pub(crate) csrf: SecretString,
+}
+
+pub(crate) async fn login(config: &Config, auth: &AuthConfig) -> anyhow::Result<Session> {
+    Auth::new(config)?.login(auth).await
+}
+
+fn client_builder(config: &Config) -> anyhow::Result<reqwest::ClientBuilder> {
+    let mut client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .default_headers(config.headers.client_headers()?);
+
+    if let Some(proxy) = config.proxy() {
+        client = client.proxy(proxy.build()?);
+    }
+
+    Ok(client)
+}
+
+pub(crate) fn client(config: &Config) -> anyhow::Result<reqwest::Client> {
+    client_builder(config)?.build().map_err(Into::into)
+}
+
+pub(crate) fn download_client(config: &Config) -> anyhow::Result<reqwest::Client> {
+    client_builder(config)?
+        .timeout(Duration::from_secs(60 * 60))
+        .read_timeout(Duration::from_secs(30))
+        .build()
+        .map_err(Into::into)
+}
+
+pub(crate) async fn check_connection(config: &Config) -> anyhow::Result<Duration> {
+    let client = client(config)?;
+    let start = std::time::Instant::now();
+    client.head(config.base.clone()).send().await?;
+
+    Ok(start.elapsed())
+}
+
+pub(crate) fn prompt_credentials() -> anyhow::Result<AuthConfig> {
+    let username = prompt("Username: ")?;
+    let password = rpassword::prompt_password("Password: ")?;
+    let email = prompt("Email (optional): ")?;
+
+    Ok(AuthConfig::User {
+        username,
+        password: password.into(),
+        email: Some(email).filter(|email| !email.is_empty()),
+        proxy: None,
+    })
+}
+
+fn prompt(message: &str) -> anyhow::Result<String> {
+    let mut stdout = std::io::stdout();
+    stdout.write_all(message.as_bytes())?;
+    stdout.flush()?;
+
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+
+    Ok(line.trim().to_owned())
+}
+
+#[derive(Deserialize)]
+struct SessionFile {
+    cookie: SecretString,
+}
+
+pub(crate) fn load_session(path: &Path) -> anyhow::Result<Option<SecretString>> {
+    if !path.exists() {
+        return Ok(None);
     }
+
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("reading `{}`", path.display()))?;
+    let session: SessionFile =
+        toml::from_str(&text).with_context(|| format!("parsing `{}`", path.display()))?;
+
+    Ok(Some(session.cookie))
+}
+
+pub(crate) fn save_session(path: &Path, cookie: &SecretString) -> anyhow::Result<()> {
+    let mut table = toml::Table::new();
+    table.insert("cookie".into(), cookie.expose_secret().into());
+    let text = toml::to_string(&table)?;
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).with_context(|| format!("creating `{}`", dir.display()))?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .with_context(|| format!("writing `{}`", path.display()))
 }
 
-pub(crate) async fn from_config(
-    config: &Config,
-) -> anyhow::Result<(reqwest::Client, SecretString)> {
-    Auth::new(config).login(&config.auth).await
+pub(crate) fn csrf_from_jar(jar: &Jar, url: &url::Url) -> Option<SecretString> {
+    let cookie = jar.cookies(url)?;
+
+    cookie
+        .to_str()
+        .ok()
+        .and_then(|s| s.split(';').find_map(|s| s.trim().strip_prefix("ct0=")))
+        .map(Into::into)
 }
 
 #[derive(Debug, Serialize)]
@@ -0,0 +1,157 @@
+use std::{collections::BTreeSet, path::Path};
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+
+use crate::{scraper::SearchMode, types::Entry};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Checkpoint {
+    pub(crate) query: String,
+    pub(crate) search_mode: SearchMode,
+    pub(crate) cursor: Option<String>,
+    #[serde(default)]
+    pub(crate) emitted: BTreeSet<String>,
+    #[serde(default)]
+    pub(crate) committed: Option<u64>,
+    #[serde(default)]
+    pub(crate) completed: bool,
+}
+
+impl Checkpoint {
+    fn new(query: &str, search_mode: SearchMode, cursor: Option<String>) -> Self {
+        Self {
+            query: query.to_owned(),
+            search_mode,
+            cursor,
+            emitted: BTreeSet::new(),
+            committed: None,
+            completed: false,
+        }
+    }
+
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading `{}`", path.display()))?;
+
+        serde_json::from_str(&text).with_context(|| format!("parsing `{}`", path.display()))
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        let partial = path.with_extension("part");
+
+        std::fs::write(&partial, text)
+            .and_then(|()| std::fs::rename(&partial, path))
+            .with_context(|| format!("writing `{}`", path.display()))
+    }
+
+    fn ensure_matches(&self, query: &str, search_mode: SearchMode) -> anyhow::Result<()> {
+        if self.query != query || self.search_mode != search_mode {
+            anyhow::bail!(
+                "checkpoint was created for `{}` ({}), not `{query}` ({search_mode})",
+                self.query,
+                self.search_mode
+            );
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn record<T: Entry>(&mut self, page: Vec<T>, cursor: Option<&str>) -> Vec<T> {
+        self.cursor = cursor.map(ToOwned::to_owned);
+
+        page.into_iter()
+            .filter(|entry| match entry.id() {
+                Some(id) => self.emitted.insert(id.to_owned()),
+                None => true,
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn open(
+    path: &Path,
+    resume: bool,
+    query: &str,
+    search_mode: SearchMode,
+    cursor: Option<String>,
+) -> anyhow::Result<Checkpoint> {
+    if resume {
+        let checkpoint = Checkpoint::load(path)?;
+        checkpoint.ensure_matches(query, search_mode)?;
+        return Ok(checkpoint);
+    }
+
+    if path.exists() {
+        anyhow::bail!(
+            "checkpoint `{}` already exists; pass --resume to continue it or remove it",
+            path.display()
+        );
+    }
+
+    Ok(Checkpoint::new(query, search_mode, cursor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Item(Option<&'static str>);
+
+    impl Entry for Item {
+        fn id(&self) -> Option<&str> {
+            self.0
+        }
+
+        fn refresh(&mut self, newer: Self) {
+            *self = newer;
+        }
+    }
+
+    #[test]
+    fn records_saves_and_resumes() {
+        let path = std::env::temp_dir().join(format!(
+            "twitter-scraper-checkpoint-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let mut checkpoint = open(&path, false, "rust", SearchMode::Latest, None).unwrap();
+        let page = checkpoint.record(vec![Item(Some("1")), Item(Some("2"))], Some("a"));
+        assert_eq!(page.len(), 2);
+        let page = checkpoint.record(
+            vec![Item(Some("2")), Item(None), Item(Some("3"))],
+            Some("b"),
+        );
+        assert_eq!(
+            page.iter().map(|item| item.0).collect::<Vec<_>>(),
+            [None, Some("3")]
+        );
+        checkpoint.committed = Some(42);
+        checkpoint.save(&path).unwrap();
+
+        assert!(open(&path, false, "rust", SearchMode::Latest, None).is_err());
+        assert!(open(&path, true, "go", SearchMode::Latest, None).is_err());
+        assert!(open(&path, true, "rust", SearchMode::Top, None).is_err());
+
+        let mut resumed = open(&path, true, "rust", SearchMode::Latest, None).unwrap();
+        assert_eq!(resumed.cursor.as_deref(), Some("b"));
+        assert_eq!(
+            resumed.emitted,
+            BTreeSet::from(["1", "2", "3"].map(ToOwned::to_owned))
+        );
+        assert_eq!(resumed.committed, Some(42));
+        assert!(!resumed.completed);
+
+        resumed.record::<Item>(Vec::new(), None);
+        resumed.completed = true;
+        resumed.save(&path).unwrap();
+        let finished = open(&path, true, "rust", SearchMode::Latest, None).unwrap();
+        assert_eq!(finished.cursor, None);
+        assert!(finished.completed);
+        assert!(!path.with_extension("part").exists());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+
+use secrecy::SecretString;
+use serde::{
+    de::{Error as _, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+const SOURCES: &[&str] = &["env", "file", "keyring"];
+
+pub(crate) enum SecretSource {
+    Inline(SecretString),
+    Env { env: String },
+    File { file: PathBuf },
+    Keyring { keyring: BTreeMap<String, String> },
+}
+
+impl<'de> Deserialize<'de> for SecretSource {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(SourceVisitor)
+    }
+}
+
+struct SourceVisitor;
+
+impl<'de> Visitor<'de> for SourceVisitor {
+    type Value = SecretSource;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a secret string or a table with one of `env`, `file` or `keyring`")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(SecretSource::Inline(value.into()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let Some(key) = map.next_key::<String>()? else {
+            return Err(A::Error::custom(
+                "empty secret table, expected one of `env`, `file` or `keyring`",
+            ));
+        };
+
+        let source = match key.as_str() {
+            "env" => SecretSource::Env {
+                env: map.next_value()?,
+            },
+            "file" => SecretSource::File {
+                file: map.next_value()?,
+            },
+            "keyring" => SecretSource::Keyring {
+                keyring: map.next_value()?,
+            },
+            _ => return Err(A::Error::unknown_field(&key, SOURCES)),
+        };
+
+        if let Some(other) = map.next_key::<String>()? {
+            return Err(A::Error::custom(format!(
+                "secret table sets both `{key}` and `{other}`, expected only one of `env`, `file` or `keyring`"
+            )));
+        }
+
+        Ok(source)
+    }
+}
+
+impl SecretSource {
+    pub(crate) fn resolve(self) -> Result<SecretString, String> {
+        match self {
+            Self::Inline(secret) => Ok(secret),
+            Self::Env { env } => std::env::var(&env)
+                .map(Into::into)
+                .map_err(|error| format!("reading environment variable `{env}`: {error}")),
+            Self::File { file } => std::fs::read_to_string(&file)
+                .map(|text| text.trim_end_matches(['\r', '\n']).into())
+                .map_err(|error| format!("reading `{}`: {error}", file.display())),
+            Self::Keyring { keyring } => lookup_keyring(&keyring),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn lookup_keyring(attributes: &BTreeMap<String, String>) -> Result<SecretString, String> {
+    if attributes.is_empty() {
+        return Err("keyring lookup needs at least one attribute".to_owned());
+    }
+
+    let output = std::process::Command::new("secret-tool")
+        .arg("lookup")
+        .args(attributes.iter().flat_map(|(key, value)| [key, value]))
+        .output()
+        .map_err(|error| format!("running `secret-tool`: {error}"))?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(format!("no keyring secret matches {attributes:?}"));
+    }
+
+    String::from_utf8(output.stdout)
+        .map(Into::into)
+        .map_err(|_| "keyring secret is not valid UTF-8".to_owned())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lookup_keyring(_: &BTreeMap<String, String>) -> Result<SecretString, String> {
+    Err("keyring lookups are only supported on Linux".to_owned())
+}
+
+pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<SecretString, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    SecretSource::deserialize(deserializer)?
+        .resolve()
+        .map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::ExposeSecret as _;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Holder {
+        #[serde(deserialize_with = "deserialize")]
+        password: SecretString,
+    }
+
+    fn resolve(text: &str) -> Result<String, String> {
+        toml::from_str::<Holder>(text)
+            .map(|holder| holder.password.expose_secret().to_owned())
+            .map_err(|error| error.message().to_owned())
+    }
+
+    #[test]
+    fn reads_inline_env_and_file_secrets() {
+        let name = format!("TWITTER_SCRAPER_TEST_SECRET_{}", std::process::id());
+        std::env::set_var(&name, "from env");
+        let path =
+            std::env::temp_dir().join(format!("twitter-scraper-secret-{}", std::process::id()));
+        std::fs::write(&path, "from file\r\n").unwrap();
+
+        assert_eq!(resolve(r#"password = "inline""#).unwrap(), "inline");
+        assert_eq!(
+            resolve(&format!(r#"password = {{ env = "{name}" }}"#)).unwrap(),
+            "from env"
+        );
+        assert_eq!(
+            resolve(&format!(r#"password = {{ file = '{}' }}"#, path.display())).unwrap(),
+            "from file"
+        );
+
+        std::env::remove_var(&name);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reports_missing_sources_and_typos() {
+        let error =
+            resolve(r#"password = { env = "TWITTER_SCRAPER_TEST_UNSET_VARIABLE" }"#).unwrap_err();
+        assert!(
+            error.starts_with("reading environment variable `TWITTER_SCRAPER_TEST_UNSET_VARIABLE`"),
+            "{error}"
+        );
+
+        let error =
+            resolve(r#"password = { file = "/nonexistent/twitter-scraper/secret" }"#).unwrap_err();
+        assert!(
+            error.starts_with("reading `/nonexistent/twitter-scraper/secret`"),
+            "{error}"
+        );
+
+        assert_eq!(
+            resolve(r#"password = { enviroment = "TOKEN" }"#).unwrap_err(),
+            "unknown field `enviroment`, expected one of `env`, `file`, `keyring`"
+        );
+        assert!(resolve(r#"password = { env = "A", file = "b" }"#)
+            .unwrap_err()
+            .contains("both `env` and `file`"));
+        assert!(resolve("password = {}")
+            .unwrap_err()
+            .starts_with("empty secret table"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn keyring_lookups_need_attributes() {
+        assert_eq!(
+            resolve("password = { keyring = {} }").unwrap_err(),
+            "keyring lookup needs at least one attribute"
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum HeaderProfile {
+    #[default]
+    Chrome,
+    Firefox,
+    MobileWeb,
+}
+
+impl HeaderProfile {
+    fn headers(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Chrome => &[
+                (
+                    "user-agent",
+                    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36",
+                ),
+                (
+                    "sec-ch-ua",
+                    r#""Google Chrome";v="131", "Chromium";v="131", "Not_A Brand";v="24""#,
+                ),
+                ("sec-ch-ua-mobile", "?0"),
+                ("sec-ch-ua-platform", r#""Windows""#),
+            ],
+            Self::Firefox => &[
+                (
+                    "user-agent",
+                    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:133.0) Gecko/20100101 Firefox/133.0",
+                ),
+                ("te", "trailers"),
+            ],
+            Self::MobileWeb => &[
+                (
+                    "user-agent",
+                    "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Mobile Safari/537.36",
+                ),
+                (
+                    "sec-ch-ua",
+                    r#""Google Chrome";v="131", "Chromium";v="131", "Not_A Brand";v="24""#,
+                ),
+                ("sec-ch-ua-mobile", "?1"),
+                ("sec-ch-ua-platform", r#""Android""#),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct HeaderConfig {
+    pub(crate) profile: HeaderProfile,
+    pub(crate) language: String,
+    pub(crate) extra: BTreeMap<String, String>,
+}
+
+impl Default for HeaderConfig {
+    fn default() -> Self {
+        Self {
+            profile: HeaderProfile::default(),
+            language: "en".to_owned(),
+            extra: BTreeMap::new(),
+        }
+    }
+}
+
+impl HeaderConfig {
+    pub(crate) fn client_headers(&self) -> anyhow::Result<HeaderMap> {
+        let language = [("accept-language", self.language.as_str())];
+
+        header_map(
+            language
+                .into_iter()
+                .chain(self.profile.headers().iter().copied()),
+        )
+    }
+
+    pub(crate) fn api_headers(&self) -> anyhow::Result<HeaderMap> {
+        let common = [
+            ("accept", "*/*"),
+            ("origin", "https://x.com"),
+            ("referer", "https://x.com/"),
+            ("sec-fetch-dest", "empty"),
+            ("sec-fetch-mode", "cors"),
+            ("sec-fetch-site", "same-site"),
+            ("x-twitter-active-user", "yes"),
+            ("x-twitter-client-language", self.language.as_str()),
+        ];
+
+        let extra = self
+            .extra
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()));
+
+        header_map(common.into_iter().chain(extra))
+    }
+}
+
+fn header_map<'a>(
+    headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> anyhow::Result<HeaderMap> {
+    let mut map = HeaderMap::new();
+
+    for (name, value) in headers {
+        map.insert(
+            HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("invalid header name `{name}`"))?,
+            HeaderValue::from_str(value)
+                .with_context(|| format!("invalid value for header `{name}`"))?,
+        );
+    }
+
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_site_headers_off_the_client() {
+        let config = HeaderConfig {
+            profile: HeaderProfile::Firefox,
+            language: "de".to_owned(),
+            extra: BTreeMap::from([("referer".to_owned(), "https://x.com/search".to_owned())]),
+        };
+
+        let client = config.client_headers().unwrap();
+        assert!(client["user-agent"].to_str().unwrap().contains("Firefox"));
+        assert_eq!(client["accept-language"], "de");
+        for name in [
+            "origin",
+            "referer",
+            "sec-fetch-site",
+            "x-twitter-active-user",
+        ] {
+            assert!(!client.contains_key(name), "{name}");
+        }
+
+        let api = config.api_headers().unwrap();
+        assert_eq!(api["origin"], "https://x.com");
+        assert_eq!(api["referer"], "https://x.com/search");
+        assert_eq!(api["sec-fetch-mode"], "cors");
+        assert_eq!(api["x-twitter-client-language"], "de");
+        assert!(!api.contains_key("user-agent"));
+    }
+
+    #[test]
+    fn rejects_invalid_extra_headers() {
+        let config = HeaderConfig {
+            extra: BTreeMap::from([("bad header".to_owned(), "value".to_owned())]),
+            ..HeaderConfig::default()
+        };
+
+        assert!(config.api_headers().is_err());
+    }
+}
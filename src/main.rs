@@ -2,106 +2,572 @@
 #![warn(clippy::use_self)]
 
 use anyhow::Context as _;
-use types::timeline::v1::{QueryProfilesResponse, QueryTweetsResponse};
+use output::{Record, Sink, SinkOptions};
+use pagination::StopReason;
+use scraper::SearchMode;
+use types::{profile::Profile, timeline::v1::QueryTweetsResponse, tweets::Tweet};
 
 mod auth;
+mod checkpoint;
 mod config;
+mod headers;
+mod media;
+mod operations;
 mod options;
+mod output;
+mod pagination;
 mod pp;
+mod query;
 mod scraper;
+mod shard;
+mod transaction;
 mod types;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let config = config::load(concat!(env!("CARGO_PKG_NAME"), ".toml"))?;
-    let scraper = scraper::from_config(config).await?;
+    let options = options::from_args();
+    let load_config = || config::load(options.config.as_deref(), options.profile.as_deref());
 
-    match options::from_args() {
-        options::Options::Tweets {
-            mut query,
-            from,
+    match options.command {
+        options::Command::Tweets {
+            query,
             search_mode,
             count,
             cursor,
             output,
+            out,
+            columns,
             all,
+            max_empty_pages,
+            checkpoint: checkpoint_path,
+            resume,
+            limit,
+            since,
+            until,
+            max_duration,
+            shards,
+            concurrency,
+            shard_cap,
+            download_media,
+            media_concurrency,
         } => {
-            if let Some(from) = from {
-                query = format!("from:{} {}", from, query);
+            let mut query = query.build()?;
+            if let Some(since) = since {
+                query = query.since(since);
+            }
+            if let Some(until) = until {
+                query = query.until(until);
             }
+            query.validate()?;
+            let raw = query.to_string();
+            let (since, until) = (query.since_bound(), query.until_bound());
 
-            let tweets = if all {
-                let mut cursor = None;
-                let mut tweets = QueryTweetsResponse::default();
+            if shards.is_some() && (since.is_none() || until.is_none()) {
+                anyhow::bail!(
+                    "sharded search needs both bounds, from --since/--until or since:/until: in the query"
+                );
+            }
+            let search_mode = match (shards, search_mode) {
+                (Some(_), Some(search_mode)) if search_mode != SearchMode::Latest => {
+                    anyhow::bail!(
+                        "sharded search always runs in latest mode, --search-mode {search_mode} cannot be used with --shards"
+                    )
+                }
+                (Some(_), _) => SearchMode::Latest,
+                (None, search_mode) => search_mode.unwrap_or(SearchMode::Top),
+            };
 
-                loop {
-                    let new = scraper
-                        .tweets(search_mode, &query, count, cursor)
+            let config = load_config()?;
+            let media_dir = match (&output, download_media) {
+                (options::Output::Html, Some(_)) => anyhow::bail!(
+                    "--download-media cannot be used with html output, media is saved next to the archive"
+                ),
+                (options::Output::Html, None) => {
+                    Some(output::html_dir(out.as_deref())?.join("media"))
+                }
+                (_, dir) => dir,
+            };
+            let mut downloader = match &media_dir {
+                Some(dir) => Some(media::Downloader::new(
+                    auth::download_client(&config)?,
+                    dir,
+                    media_concurrency,
+                )?),
+                None => None,
+            };
+            let scraper = scraper::from_config(config).await?;
+
+            let result = async {
+                let profile = match (&output, query.author()) {
+                    (options::Output::Html, Some(author)) => find_profile(&scraper, author).await?,
+                    _ => None,
+                };
+                if let (Some(downloader), Some(profile)) = (&mut downloader, &profile) {
+                    downloader.download_profile(profile).await;
+                }
+
+                if let (Some(shards), Some(since), Some(until)) = (shards, since, until) {
+                    let options = shard::ShardOptions {
+                        shards,
+                        concurrency,
+                        cap: shard_cap,
+                        page_size: count,
+                        max_empty_pages,
+                    };
+                    let sharded = shard::search(&scraper, &query, since, until, &options)
                         .await
                         .context("failed to scrape tweets")?;
+                    let tweets = QueryTweetsResponse {
+                        tweets: sharded.tweets,
+                        next: None,
+                        previous: None,
+                    };
+                    download(downloader.as_mut(), &tweets.tweets).await;
 
-                    if new.tweets.is_empty() {
-                        break tweets;
+                    match output {
+                        options::Output::PrettyPrint => pp::tweets(&tweets),
+                        _ => {
+                            let sink_options = SinkOptions {
+                                output: &output,
+                                out: out.as_deref(),
+                                checkpoint: false,
+                                resume: false,
+                                committed: None,
+                                columns: &columns,
+                                query: &raw,
+                                search_mode,
+                                profile: profile.as_ref(),
+                            };
+                            emit(&sink_options, tweets.tweets, None).await?
+                        }
                     }
 
-                    cursor = new.next;
-                    tweets.merge(new.tweets);
-                }
-            } else {
-                scraper
-                    .tweets(search_mode, &query, count, cursor)
-                    .await
-                    .context("failed to scrape tweets")?
-            };
+                    if sharded.failed > 0 {
+                        anyhow::bail!(
+                            "{} shard windows failed, the tweets of the other windows were written",
+                            sharded.failed
+                        );
+                    }
+                } else if all {
+                    let mut checkpoint = checkpoint_path
+                        .as_deref()
+                        .map(|path| {
+                            checkpoint::open(path, resume, &raw, search_mode, cursor.clone())
+                        })
+                        .transpose()?;
+                    if let Some(checkpoint) = checkpoint
+                        .as_ref()
+                        .filter(|checkpoint| checkpoint.completed)
+                    {
+                        eprintln!("Checkpoint for `{}` is already complete", checkpoint.query);
+                        return Ok(());
+                    }
+                    let cursor = match &checkpoint {
+                        Some(checkpoint) => checkpoint.cursor.clone(),
+                        None => cursor,
+                    };
+                    let emitted = checkpoint
+                        .iter()
+                        .flat_map(|checkpoint| checkpoint.emitted.iter().cloned())
+                        .collect::<Vec<_>>();
+
+                    let mut stream = scraper
+                        .tweets_stream(&raw, search_mode)
+                        .page_size(count)
+                        .cursor(cursor)
+                        .max_empty_pages(max_empty_pages)
+                        .limit(limit)
+                        .since(since)
+                        .until(until)
+                        .max_duration(max_duration.map(Into::into))
+                        .seen(emitted);
+
+                    let mut sink = Sink::<Tweet>::open(&SinkOptions {
+                        output: &output,
+                        out: out.as_deref(),
+                        checkpoint: checkpoint.is_some(),
+                        resume,
+                        committed: checkpoint
+                            .as_ref()
+                            .and_then(|checkpoint| checkpoint.committed),
+                        columns: &columns,
+                        query: &raw,
+                        search_mode,
+                        profile: profile.as_ref(),
+                    })
+                    .await?;
+
+                    while let Some(page) = stream
+                        .next_page()
+                        .await
+                        .context("failed to scrape tweets")?
+                    {
+                        let page = match &mut checkpoint {
+                            Some(checkpoint) => checkpoint.record(page, stream.next_cursor()),
+                            None => page,
+                        };
+
+                        download(downloader.as_mut(), &page).await;
+                        sink.write(page, stream.next_cursor()).await?;
+
+                        if let (Some(checkpoint), Some(path)) = (&mut checkpoint, &checkpoint_path)
+                        {
+                            checkpoint.committed = sink.commit().await?;
+                            checkpoint.save(path)?;
+                        }
+                    }
+
+                    sink.finish().await?;
 
-            match output {
-                options::Output::PrettyPrint => pp::tweets(&tweets),
-                options::Output::Json => {
-                    let json = serde_json::to_string_pretty(&tweets)?;
-                    std::fs::write("tweets.json", json).context("writing `tweets.json`")?;
+                    if let (Some(checkpoint), Some(path)) = (&mut checkpoint, &checkpoint_path) {
+                        checkpoint.completed =
+                            stream.stop_reason().is_some_and(StopReason::is_final);
+                        checkpoint.save(path)?;
+                    }
+
+                    if let Some(reason) = stream.stop_reason() {
+                        eprintln!("Stopped paginating: {reason}");
+                    }
+                    if stream.duplicates() > 0 {
+                        eprintln!("Collapsed {} duplicate tweets", stream.duplicates());
+                    }
+                } else {
+                    let tweets = scraper
+                        .tweets(search_mode, &raw, count, cursor)
+                        .await
+                        .context("failed to scrape tweets")?;
+                    download(downloader.as_mut(), &tweets.tweets).await;
+
+                    match output {
+                        options::Output::PrettyPrint => pp::tweets(&tweets),
+                        _ => {
+                            let sink_options = SinkOptions {
+                                output: &output,
+                                out: out.as_deref(),
+                                checkpoint: false,
+                                resume: false,
+                                committed: None,
+                                columns: &columns,
+                                query: &raw,
+                                search_mode,
+                                profile: profile.as_ref(),
+                            };
+                            emit(&sink_options, tweets.tweets, tweets.next.as_deref()).await?
+                        }
+                    }
                 }
+
+                Ok(())
+            }
+            .await;
+
+            if let Some(downloader) = &downloader {
+                report(downloader);
             }
+            save_session(&scraper, result)?;
         }
-        options::Options::Profiles {
+        options::Command::Profiles {
             query,
             count,
             cursor,
             output,
+            out,
+            columns,
             all,
+            max_empty_pages,
+            checkpoint: checkpoint_path,
+            resume,
+            limit,
+            max_duration,
         } => {
-            let profiles = if all {
-                let mut cursor = None;
-                let mut profiles = QueryProfilesResponse::default();
+            let config = load_config()?;
+            let scraper = scraper::from_config(config).await?;
+
+            let result = async {
+                if all {
+                    let mut checkpoint = checkpoint_path
+                        .as_deref()
+                        .map(|path| {
+                            checkpoint::open(
+                                path,
+                                resume,
+                                &query,
+                                SearchMode::Users,
+                                cursor.clone(),
+                            )
+                        })
+                        .transpose()?;
+                    if let Some(checkpoint) = checkpoint
+                        .as_ref()
+                        .filter(|checkpoint| checkpoint.completed)
+                    {
+                        eprintln!("Checkpoint for `{}` is already complete", checkpoint.query);
+                        return Ok(());
+                    }
+                    let cursor = match &checkpoint {
+                        Some(checkpoint) => checkpoint.cursor.clone(),
+                        None => cursor,
+                    };
+                    let emitted = checkpoint
+                        .iter()
+                        .flat_map(|checkpoint| checkpoint.emitted.iter().cloned())
+                        .collect::<Vec<_>>();
 
-                loop {
-                    let new = scraper
+                    let mut stream = scraper
+                        .profiles_stream(&query)
+                        .page_size(count)
+                        .cursor(cursor)
+                        .max_empty_pages(max_empty_pages)
+                        .limit(limit)
+                        .max_duration(max_duration.map(Into::into))
+                        .seen(emitted);
+
+                    let mut sink = Sink::<Profile>::open(&SinkOptions {
+                        output: &output,
+                        out: out.as_deref(),
+                        checkpoint: checkpoint.is_some(),
+                        resume,
+                        committed: checkpoint
+                            .as_ref()
+                            .and_then(|checkpoint| checkpoint.committed),
+                        columns: &columns,
+                        query: &query,
+                        search_mode: SearchMode::Users,
+                        profile: None,
+                    })
+                    .await?;
+
+                    while let Some(page) = stream
+                        .next_page()
+                        .await
+                        .context("failed to scrape profiles")?
+                    {
+                        let page = match &mut checkpoint {
+                            Some(checkpoint) => checkpoint.record(page, stream.next_cursor()),
+                            None => page,
+                        };
+
+                        sink.write(page, stream.next_cursor()).await?;
+
+                        if let (Some(checkpoint), Some(path)) = (&mut checkpoint, &checkpoint_path)
+                        {
+                            checkpoint.committed = sink.commit().await?;
+                            checkpoint.save(path)?;
+                        }
+                    }
+
+                    sink.finish().await?;
+
+                    if let (Some(checkpoint), Some(path)) = (&mut checkpoint, &checkpoint_path) {
+                        checkpoint.completed =
+                            stream.stop_reason().is_some_and(StopReason::is_final);
+                        checkpoint.save(path)?;
+                    }
+
+                    if let Some(reason) = stream.stop_reason() {
+                        eprintln!("Stopped paginating: {reason}");
+                    }
+                    if stream.duplicates() > 0 {
+                        eprintln!("Collapsed {} duplicate profiles", stream.duplicates());
+                    }
+                } else {
+                    let profiles = scraper
                         .profiles(&query, count, cursor)
                         .await
                         .context("failed to scrape tweets")?;
 
-                    if new.profiles.is_empty() {
-                        break profiles;
+                    match output {
+                        options::Output::PrettyPrint => pp::profiles(&profiles),
+                        _ => {
+                            let sink_options = SinkOptions {
+                                output: &output,
+                                out: out.as_deref(),
+                                checkpoint: false,
+                                resume: false,
+                                committed: None,
+                                columns: &columns,
+                                query: &query,
+                                search_mode: SearchMode::Users,
+                                profile: None,
+                            };
+                            emit(&sink_options, profiles.profiles, profiles.next.as_deref()).await?
+                        }
                     }
+                }
+
+                Ok(())
+            }
+            .await;
+
+            save_session(&scraper, result)?;
+        }
+        options::Command::Auth { command } => match command {
+            options::AuthCommand::Check => {
+                let config = load_config()?;
+                if let Some(proxy) = config.proxy() {
+                    let latency = auth::check_connection(&config)
+                        .await
+                        .with_context(|| format!("proxy `{}` is not reachable", proxy.display()))?;
 
-                    cursor = new.next;
-                    profiles.merge(new.profiles);
+                    println!("Proxy: {} ({} ms)", proxy.display(), latency.as_millis());
                 }
-            } else {
-                scraper
-                    .profiles(&query, count, cursor)
+
+                let scraper = scraper::from_config(config).await?;
+                let profile = scraper
+                    .verify_credentials()
                     .await
-                    .context("failed to scrape tweets")?
-            };
+                    .context("session is not valid")?;
 
-            match output {
-                options::Output::PrettyPrint => pp::profiles(&profiles),
-                options::Output::Json => {
-                    let json = serde_json::to_string_pretty(&profiles)?;
-                    std::fs::write("profiles.json", json).context("writing `profiles.json`")?;
-                }
+                pp::profile(&profile);
+                scraper.save_session()?;
+            }
+            options::AuthCommand::Login => {
+                let config = load_config()?;
+                let auth = match &config.auth {
+                    config::AuthConfig::User { .. } => None,
+                    config::AuthConfig::Cookie { .. } => Some(auth::prompt_credentials()?),
+                };
+
+                let scraper = scraper::login(config, auth.as_ref())
+                    .await
+                    .context("failed to log in")?;
+                let profile = scraper
+                    .verify_credentials()
+                    .await
+                    .context("session is not valid")?;
+
+                scraper.save_session()?;
+                pp::profile(&profile);
+            }
+            options::AuthCommand::Logout => {
+                let config = load_config()?;
+                let scraper = scraper::from_config(config).await?;
+                scraper.logout().await.context("failed to log out")?;
+            }
+        },
+        options::Command::Ops { command } => match command {
+            options::OpsCommand::Refresh { bundle, output } => {
+                let config = load_config()?;
+                let client = auth::client(&config)?;
+                let (discovered, feature_values) = operations::discover(&client, bundle.as_deref())
+                    .await
+                    .context("failed to discover operations")?;
+
+                let path = output.unwrap_or_else(|| config.operations_path());
+                let mut overrides = operations::overrides(&path)?;
+                let mut known = operations::Registry::builtin();
+                known.merge(overrides.clone());
+                let count = discovered.len();
+
+                overrides.replace(known.refresh(discovered, &feature_values));
+                overrides.save(&path)?;
+
+                println!("Refreshed {count} operations in `{}`", path.display());
+            }
+        },
+        options::Command::Media {
+            input,
+            dir,
+            concurrency,
+        } => {
+            let config = load_config()?;
+            let tweets = media::read_tweets(&input)?;
+            let mut downloader =
+                media::Downloader::new(auth::download_client(&config)?, &dir, concurrency)?;
+
+            downloader.download(&tweets).await;
+            report(&downloader);
+
+            let failed = downloader.summary().failed;
+            if failed > 0 {
+                anyhow::bail!("{failed} media files failed to download");
             }
         }
+        options::Command::Db { command } => match command {
+            #[cfg(feature = "postgres")]
+            options::DbCommand::Migrate { url } => {
+                let mut client = output::postgres::connect(&url).await?;
+                let applied = output::postgres::migrate(&mut client)
+                    .await
+                    .context("failed to migrate the database")?;
+
+                match applied.len() {
+                    0 => println!("Database is up to date"),
+                    count => println!("Applied {count} migrations: {}", applied.join(", ")),
+                }
+            }
+            #[cfg(not(feature = "postgres"))]
+            options::DbCommand::Migrate { .. } => {
+                anyhow::bail!("`db migrate` needs a build with the `postgres` feature")
+            }
+        },
     }
 
     Ok(())
 }
+
+fn save_session(scraper: &scraper::Scraper, result: anyhow::Result<()>) -> anyhow::Result<()> {
+    match result {
+        Ok(()) => scraper.save_session(),
+        Err(error) => {
+            if let Err(error) = scraper.save_session() {
+                eprintln!("warning: failed to save the session: {error:#}");
+            }
+            Err(error)
+        }
+    }
+}
+
+async fn find_profile(
+    scraper: &scraper::Scraper,
+    username: &str,
+) -> anyhow::Result<Option<Profile>> {
+    let profiles = scraper
+        .profiles(username, 10, None)
+        .await
+        .context("failed to look up the archived profile")?;
+
+    let profile = profiles
+        .profiles
+        .into_iter()
+        .find(|profile| profile.username.eq_ignore_ascii_case(username));
+    if profile.is_none() {
+        eprintln!("warning: profile @{username} was not found, the archive has no profile header");
+    }
+
+    Ok(profile)
+}
+
+async fn download(downloader: Option<&mut media::Downloader>, tweets: &[Tweet]) {
+    if let Some(downloader) = downloader {
+        downloader.download(tweets).await;
+    }
+}
+
+fn report(downloader: &media::Downloader) {
+    let summary = downloader.summary();
+    eprintln!(
+        "Downloaded {} media files to `{}` ({} duplicates, {} already present)",
+        summary.downloaded,
+        downloader.dir().display(),
+        summary.duplicates,
+        summary.existing
+    );
+
+    if summary.failed > 0 {
+        eprintln!(
+            "Failed to download {} media files, rerun to retry them",
+            summary.failed
+        );
+    }
+}
+
+async fn emit<T: Record>(
+    options: &SinkOptions<'_>,
+    items: Vec<T>,
+    next: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut sink = Sink::open(options).await?;
+    sink.write(items, next).await?;
+    sink.finish().await
+}
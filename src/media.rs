@@ -0,0 +1,837 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use futures::StreamExt as _;
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::types::{
+    profile::Profile,
+    tweets::{Photo, Tweet, Video},
+};
+
+mod hls;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Kind {
+    Photo,
+    Video,
+}
+
+impl Kind {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Photo => "jpg",
+            Self::Video => "mp4",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Source {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tweet_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user_id: Option<String>,
+    media_id: String,
+    url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Sidecar {
+    file: String,
+    sha256: String,
+    size: u64,
+    kind: Kind,
+    sources: Vec<Source>,
+}
+
+impl Sidecar {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading `{}`", path.display()))?;
+
+        serde_json::from_str(&text).with_context(|| format!("parsing `{}`", path.display()))
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        let partial = path.with_extension("part");
+
+        std::fs::write(&partial, text)
+            .and_then(|()| std::fs::rename(&partial, path))
+            .with_context(|| format!("writing `{}`", path.display()))
+    }
+
+    fn link(&mut self, sources: Vec<Source>) -> bool {
+        let before = self.sources.len();
+        for source in sources {
+            if !self.sources.contains(&source) {
+                self.sources.push(source);
+            }
+        }
+
+        self.sources.len() != before
+    }
+}
+
+struct Target {
+    kind: Kind,
+    playlist: bool,
+    sources: Vec<Source>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Summary {
+    pub(crate) downloaded: usize,
+    pub(crate) duplicates: usize,
+    pub(crate) existing: usize,
+    pub(crate) failed: usize,
+}
+
+pub(crate) struct Downloader {
+    client: reqwest::Client,
+    dir: PathBuf,
+    concurrency: usize,
+    known: HashMap<String, PathBuf>,
+    summary: Summary,
+}
+
+impl Downloader {
+    pub(crate) fn new(
+        client: reqwest::Client,
+        dir: &Path,
+        concurrency: usize,
+    ) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir).with_context(|| format!("creating `{}`", dir.display()))?;
+
+        Ok(Self {
+            client,
+            dir: dir.to_owned(),
+            concurrency,
+            known: index(dir)?,
+            summary: Summary::default(),
+        })
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub(crate) fn summary(&self) -> Summary {
+        self.summary
+    }
+
+    pub(crate) async fn download(&mut self, tweets: &[Tweet]) {
+        self.fetch_all(targets(tweets)).await
+    }
+
+    pub(crate) async fn download_profile(&mut self, profile: &Profile) {
+        let images = [
+            ("avatar", &profile.profile_image_url),
+            ("banner", &profile.profile_banner_url),
+        ];
+
+        let targets = images
+            .into_iter()
+            .filter_map(|(name, url)| {
+                let url = url.clone()?;
+                let source = Source {
+                    tweet_id: None,
+                    user_id: Some(profile.id.clone()),
+                    media_id: format!("{name}-{}", profile.id),
+                    url: url.clone(),
+                };
+                let target = Target {
+                    kind: Kind::Photo,
+                    playlist: false,
+                    sources: vec![source],
+                };
+
+                Some((url, target))
+            })
+            .collect();
+
+        self.fetch_all(targets).await
+    }
+
+    async fn fetch_all(&mut self, targets: BTreeMap<String, Target>) {
+        let summary = &mut self.summary;
+        let mut fetches = Vec::new();
+
+        for (url, target) in targets {
+            match self.known.get(&url) {
+                Some(path) => match relink(path, target.sources) {
+                    Ok(()) => summary.existing += 1,
+                    Err(error) => {
+                        eprintln!("warning: failed to record `{url}`: {error:#}");
+                        summary.failed += 1;
+                    }
+                },
+                None => fetches.push((url, target)),
+            }
+        }
+
+        let (client, dir, concurrency) = (&self.client, self.dir.as_path(), self.concurrency);
+        let mut results = futures::stream::iter(fetches)
+            .map(|(url, target)| async move {
+                let media_id = &target.sources[0].media_id;
+                let part = if target.playlist {
+                    hls::fetch(client, dir, &url, media_id, concurrency)
+                        .await
+                        .map(|track| (track.part, track.extension.to_owned()))
+                } else {
+                    fetch(client, dir, &url, media_id)
+                        .await
+                        .map(|part| (part, extension(&url, target.kind)))
+                };
+                (url, target, part)
+            })
+            .buffer_unordered(concurrency.max(1));
+
+        while let Some((url, target, part)) = results.next().await {
+            let stored = part.and_then(|(part, extension)| store(dir, &part, &extension, target));
+
+            match stored {
+                Ok((sidecar, duplicate)) => {
+                    if duplicate {
+                        summary.duplicates += 1;
+                    } else {
+                        summary.downloaded += 1;
+                    }
+                    self.known.insert(url, sidecar);
+                }
+                Err(error) => {
+                    eprintln!("warning: failed to download `{url}`: {error:#}");
+                    summary.failed += 1;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct Library {
+    files: HashMap<String, String>,
+}
+
+impl Library {
+    pub(crate) fn open(dir: &Path) -> anyhow::Result<Self> {
+        if !dir.exists() {
+            return Ok(Self {
+                files: HashMap::new(),
+            });
+        }
+
+        let files = index(dir)?
+            .into_iter()
+            .filter_map(|(url, sidecar)| {
+                let file = sidecar.file_stem()?.to_str()?.to_owned();
+                Some((url, file))
+            })
+            .collect();
+
+        Ok(Self { files })
+    }
+
+    pub(crate) fn image(&self, url: &str) -> Option<&str> {
+        self.files.get(url).map(String::as_str)
+    }
+
+    pub(crate) fn photo(&self, photo: &Photo) -> Option<&str> {
+        self.image(&original(&photo.url))
+    }
+
+    pub(crate) fn video(&self, video: &Video) -> Option<&str> {
+        let url = video.url.as_ref().or(video.playlist.as_ref())?;
+        self.image(url)
+    }
+}
+
+fn relink(path: &Path, sources: Vec<Source>) -> anyhow::Result<()> {
+    let mut sidecar = Sidecar::load(path)?;
+    if sidecar.link(sources) {
+        sidecar.save(path)?;
+    }
+
+    Ok(())
+}
+
+fn index(dir: &Path) -> anyhow::Result<HashMap<String, PathBuf>> {
+    let mut known = HashMap::new();
+
+    for entry in std::fs::read_dir(dir).with_context(|| format!("reading `{}`", dir.display()))? {
+        let path = entry?.path();
+        if !is_sidecar(&path) {
+            continue;
+        }
+
+        match Sidecar::load(&path) {
+            Ok(sidecar) => {
+                for source in sidecar.sources {
+                    known.insert(source.url, path.clone());
+                }
+            }
+            Err(error) => eprintln!("warning: skipping media sidecar: {error:#}"),
+        }
+    }
+
+    Ok(known)
+}
+
+fn is_sidecar(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let Some((sha256, extension)) = name
+        .strip_suffix(".json")
+        .and_then(|file| file.split_once('.'))
+    else {
+        return false;
+    };
+
+    sha256.len() == 64
+        && sha256.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+        && !extension.is_empty()
+        && extension.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+pub(crate) fn read_tweets(path: &Path) -> anyhow::Result<Vec<Tweet>> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("reading `{}`", path.display()))?;
+
+    if let Ok(mut document) = serde_json::from_str::<serde_json::Map<_, _>>(&text) {
+        if let Some(tweets) = document.remove("tweets") {
+            return serde_json::from_value(tweets)
+                .with_context(|| format!("parsing `{}`", path.display()));
+        }
+    }
+
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("parsing line {} of `{}`", index + 1, path.display()))
+        })
+        .collect()
+}
+
+fn targets(tweets: &[Tweet]) -> BTreeMap<String, Target> {
+    let mut targets = BTreeMap::<String, Target>::new();
+    let mut pending = tweets.iter().collect::<Vec<_>>();
+
+    while let Some(tweet) = pending.pop() {
+        pending.extend(tweet.quoted_status.as_deref());
+        pending.extend(tweet.retweeted_status.as_deref());
+        pending.extend(&tweet.thread);
+
+        let Some(tweet_id) = &tweet.id else {
+            continue;
+        };
+
+        let photos = tweet
+            .photos
+            .iter()
+            .map(|photo| (Kind::Photo, false, &photo.id, original(&photo.url)));
+        let videos = tweet.videos.iter().filter_map(|video| match &video.url {
+            Some(url) => Some((Kind::Video, false, &video.id, url.clone())),
+            None => Some((Kind::Video, true, &video.id, video.playlist.clone()?)),
+        });
+
+        for (kind, playlist, media_id, url) in photos.chain(videos) {
+            let source = Source {
+                tweet_id: Some(tweet_id.clone()),
+                user_id: None,
+                media_id: media_id.clone(),
+                url: url.clone(),
+            };
+
+            let target = targets.entry(url).or_insert_with(|| Target {
+                kind,
+                playlist,
+                sources: Vec::new(),
+            });
+            if !target.sources.contains(&source) {
+                target.sources.push(source);
+            }
+        }
+    }
+
+    targets
+}
+
+fn original(url: &str) -> String {
+    let Ok(mut parsed) = url::Url::parse(url) else {
+        return url.to_owned();
+    };
+
+    let pairs = parsed
+        .query_pairs()
+        .filter(|(name, _)| name != "name")
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    parsed
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("name", "orig");
+
+    parsed.into()
+}
+
+fn extension(url: &str, kind: Kind) -> String {
+    let path = url::Url::parse(url)
+        .map(|url| url.path().to_owned())
+        .unwrap_or_default();
+
+    Path::new(&path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .filter(|extension| extension.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or(kind.extension())
+        .to_ascii_lowercase()
+}
+
+async fn fetch(
+    client: &reqwest::Client,
+    dir: &Path,
+    url: &str,
+    media_id: &str,
+) -> anyhow::Result<PathBuf> {
+    let part = dir.join(format!("{media_id}.part"));
+    let offset = std::fs::metadata(&part).map_or(0, |metadata| metadata.len());
+
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(header::RANGE, format!("bytes={offset}-"));
+    }
+
+    let response = request.send().await?;
+    if offset > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(part);
+    }
+
+    let mut response = response.error_for_status()?;
+    let append = response.status() == StatusCode::PARTIAL_CONTENT;
+
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&part)
+        .with_context(|| format!("opening `{}`", part.display()))?;
+    let mut writer = std::io::BufWriter::new(file);
+
+    while let Some(chunk) = response.chunk().await? {
+        writer.write_all(&chunk)?;
+    }
+    writer.flush()?;
+
+    Ok(part)
+}
+
+fn store(
+    dir: &Path,
+    part: &Path,
+    extension: &str,
+    target: Target,
+) -> anyhow::Result<(PathBuf, bool)> {
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut std::fs::File::open(part)?, &mut hasher)?;
+    let sha256 = format!("{:x}", hasher.finalize());
+
+    let file = format!("{sha256}.{extension}");
+    let path = dir.join(&file);
+    let sidecar_path = dir.join(format!("{file}.json"));
+
+    let duplicate = path.exists();
+    if duplicate {
+        std::fs::remove_file(part)?;
+    } else {
+        std::fs::rename(part, &path).with_context(|| format!("writing `{}`", path.display()))?;
+    }
+
+    let mut sidecar = if sidecar_path.exists() {
+        Sidecar::load(&sidecar_path)?
+    } else {
+        Sidecar {
+            file,
+            sha256,
+            size,
+            kind: target.kind,
+            sources: Vec::new(),
+        }
+    };
+    sidecar.link(target.sources);
+    sidecar.save(&sidecar_path)?;
+
+    Ok((sidecar_path, duplicate))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead as _, BufReader},
+        net::TcpListener,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::types::tweets::Video;
+
+    type Requests = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+    fn serve(files: &'static [(&'static str, &'static [u8])]) -> (String, Requests) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Requests::default();
+
+        let log = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let target = line.split(' ').nth(1).unwrap().to_owned();
+
+                let mut range = None;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("range") {
+                            range = Some(value.trim().to_owned());
+                        }
+                    }
+                }
+                log.lock().unwrap().push((target.clone(), range.clone()));
+
+                let path = target.split('?').next().unwrap();
+                let Some((_, body)) = files.iter().find(|(name, _)| *name == path) else {
+                    write!(
+                        stream,
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    )
+                    .unwrap();
+                    continue;
+                };
+
+                let start = range
+                    .and_then(|range| {
+                        range
+                            .strip_prefix("bytes=")?
+                            .strip_suffix('-')?
+                            .parse()
+                            .ok()
+                    })
+                    .unwrap_or(0);
+                let status = match start {
+                    0 => "200 OK",
+                    _ => "206 Partial Content",
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len() - start
+                )
+                .unwrap();
+                stream.write_all(&body[start..]).unwrap();
+            }
+        });
+
+        (base, requests)
+    }
+
+    fn tweet(id: &str, photos: &[String], videos: &[String]) -> Tweet {
+        let photos = photos
+            .iter()
+            .enumerate()
+            .map(|(index, url)| serde_json::json!({ "id": format!("{id}-p{index}"), "url": url }))
+            .collect::<Vec<_>>();
+        let videos = videos
+            .iter()
+            .enumerate()
+            .map(|(index, url)| {
+                serde_json::json!({ "id": format!("{id}-v{index}"), "preview": "", "url": url })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "hashtags": [],
+            "urls": [],
+            "mentions": [],
+            "photos": photos,
+            "videos": videos,
+            "thread": [],
+        }))
+        .unwrap()
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "twitter-scraper-media-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[tokio::test]
+    async fn downloads_originals_and_dedupes_by_content() {
+        const FILES: &[(&str, &[u8])] = &[
+            ("/media/a.jpg", b"same image"),
+            ("/media/b.jpg", b"same image"),
+            ("/video/c.mp4", b"video bytes"),
+        ];
+        let (base, requests) = serve(FILES);
+        let dir = scratch("dedupe");
+
+        let tweets = [
+            tweet("1", &[format!("{base}/media/a.jpg")], &[]),
+            tweet(
+                "2",
+                &[format!("{base}/media/b.jpg?format=jpg&name=small")],
+                &[],
+            ),
+            tweet("3", &[], &[format!("{base}/video/c.mp4")]),
+        ];
+
+        let mut downloader = Downloader::new(reqwest::Client::new(), &dir, 2).unwrap();
+        downloader.download(&tweets).await;
+
+        let summary = downloader.summary();
+        assert_eq!((summary.downloaded, summary.duplicates), (2, 1));
+
+        let mut paths = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "/media/a.jpg?name=orig",
+                "/media/b.jpg?format=jpg&name=orig",
+                "/video/c.mp4"
+            ]
+        );
+
+        let hash = format!("{:x}", Sha256::digest(b"same image"));
+        let image = dir.join(format!("{hash}.jpg"));
+        assert_eq!(std::fs::read(&image).unwrap(), b"same image");
+
+        let sidecar = Sidecar::load(&dir.join(format!("{hash}.jpg.json"))).unwrap();
+        assert_eq!(sidecar.kind, Kind::Photo);
+        assert_eq!(
+            sidecar
+                .sources
+                .iter()
+                .filter_map(|source| source.tweet_id.as_deref())
+                .collect::<Vec<_>>(),
+            ["1", "2"]
+        );
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 4);
+
+        let mut again = Downloader::new(reqwest::Client::new(), &dir, 2).unwrap();
+        again.download(&tweets).await;
+        assert_eq!(again.summary().existing, 3);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn downloads_hls_playlists() {
+        const FILES: &[(&str, &[u8])] = &[
+            (
+                "/pl/master.m3u8",
+                b"#EXTM3U\n#EXT-X-MEDIA:NAME=\"Audio\",TYPE=AUDIO,GROUP-ID=\"aac\",URI=\"audio/media.m3u8\"\n#EXT-X-STREAM-INF:BANDWIDTH=256000\nlow/media.m3u8\n#EXT-X-STREAM-INF:BANDWIDTH=832000\nmid/media.m3u8\n#EXT-X-STREAM-INF:BANDWIDTH=2176000,AUDIO=\"aac\"\nhigh/media.m3u8\n",
+            ),
+            (
+                "/pl/mid/media.m3u8",
+                b"#EXTM3U\n#EXTINF:3.0,\n0.ts\n#EXTINF:3.0,\n1.ts\n#EXTINF:1.0,\n2.ts\n#EXT-X-ENDLIST\n",
+            ),
+            ("/pl/mid/0.ts", b"first "),
+            ("/pl/mid/1.ts", b"second "),
+            ("/pl/mid/2.ts", b"third"),
+            (
+                "/fmp4/media.m3u8",
+                b"#EXTM3U\n#EXT-X-MAP:URI=\"init.mp4\"\n#EXTINF:3.0,\n0.m4s\n#EXTINF:1.0,\n1.m4s\n#EXT-X-ENDLIST\n",
+            ),
+            ("/fmp4/init.mp4", b"moov "),
+            ("/fmp4/0.m4s", b"moof0 "),
+            ("/fmp4/1.m4s", b"moof1"),
+            (
+                "/split/master.m3u8",
+                b"#EXTM3U\n#EXT-X-MEDIA:NAME=\"Audio\",TYPE=AUDIO,GROUP-ID=\"aac\",URI=\"audio.m3u8\"\n#EXT-X-STREAM-INF:BANDWIDTH=256000,AUDIO=\"aac\"\nvideo.m3u8\n",
+            ),
+        ];
+        let (base, requests) = serve(FILES);
+        let dir = scratch("hls");
+
+        let mut tweet = tweet("1", &[], &[]);
+        for (id, path) in [
+            ("ts", "/pl/master.m3u8"),
+            ("fmp4", "/fmp4/media.m3u8"),
+            ("split", "/split/master.m3u8"),
+        ] {
+            tweet.videos.push(Video {
+                id: id.to_owned(),
+                preview: String::new(),
+                url: None,
+                playlist: Some(format!("{base}{path}")),
+            });
+        }
+
+        let mut downloader = Downloader::new(reqwest::Client::new(), &dir, 2).unwrap();
+        downloader.download(&[tweet]).await;
+
+        let summary = downloader.summary();
+        assert_eq!((summary.downloaded, summary.failed), (2, 1));
+        assert!(!requests.lock().unwrap().iter().any(|(path, _)| {
+            path.starts_with("/pl/low")
+                || path.starts_with("/pl/high")
+                || path.starts_with("/pl/audio")
+                || path.starts_with("/split/") && path != "/split/master.m3u8"
+        }));
+
+        let hash = format!("{:x}", Sha256::digest(b"first second third"));
+        assert_eq!(
+            std::fs::read(dir.join(format!("{hash}.ts"))).unwrap(),
+            b"first second third"
+        );
+        let sidecar = Sidecar::load(&dir.join(format!("{hash}.ts.json"))).unwrap();
+        assert_eq!(sidecar.kind, Kind::Video);
+        assert_eq!(sidecar.sources[0].url, format!("{base}/pl/master.m3u8"));
+
+        let hash = format!("{:x}", Sha256::digest(b"moov moof0 moof1"));
+        assert_eq!(
+            std::fs::read(dir.join(format!("{hash}.mp4"))).unwrap(),
+            b"moov moof0 moof1"
+        );
+        let sidecar = Sidecar::load(&dir.join(format!("{hash}.mp4.json"))).unwrap();
+        assert_eq!(sidecar.sources[0].media_id, "fmp4");
+
+        assert!(!dir.join("ts.hls").exists());
+        assert!(!dir.join("fmp4.hls").exists());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 4);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn collects_media_from_quotes_retweets_and_threads() {
+        let mut quoted = tweet("2", &["https://pbs.twimg.com/media/q.jpg".to_owned()], &[]);
+        quoted.retweeted_status = Some(Box::new(tweet(
+            "3",
+            &[],
+            &["https://video.twimg.com/r.mp4".to_owned()],
+        )));
+        let mut top = tweet("1", &["https://pbs.twimg.com/media/a.jpg".to_owned()], &[]);
+        top.quoted_status = Some(Box::new(quoted));
+        top.thread = vec![
+            top.clone(),
+            tweet("4", &["https://pbs.twimg.com/media/t.jpg".to_owned()], &[]),
+        ];
+
+        let targets = targets(&[top]);
+        let sources = |url: &str| {
+            targets[url]
+                .sources
+                .iter()
+                .filter_map(|source| source.tweet_id.as_deref())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(targets.len(), 4);
+        assert_eq!(
+            sources("https://pbs.twimg.com/media/a.jpg?name=orig"),
+            ["1"]
+        );
+        assert_eq!(
+            sources("https://pbs.twimg.com/media/q.jpg?name=orig"),
+            ["2"]
+        );
+        assert_eq!(sources("https://video.twimg.com/r.mp4"), ["3"]);
+        assert_eq!(
+            sources("https://pbs.twimg.com/media/t.jpg?name=orig"),
+            ["4"]
+        );
+    }
+
+    #[tokio::test]
+    async fn resumes_partial_downloads() {
+        const BODY: &[u8] = &[7; 1000];
+        const FILES: &[(&str, &[u8])] = &[("/video/long.mp4", BODY)];
+        let (base, requests) = serve(FILES);
+        let dir = scratch("resume");
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("1-v0.part"), &BODY[..400]).unwrap();
+
+        let tweets = [tweet("1", &[], &[format!("{base}/video/long.mp4")])];
+        let mut downloader = Downloader::new(reqwest::Client::new(), &dir, 1).unwrap();
+        downloader.download(&tweets).await;
+
+        assert_eq!(
+            requests.lock().unwrap().as_slice(),
+            [("/video/long.mp4".to_owned(), Some("bytes=400-".to_owned()))]
+        );
+
+        let hash = format!("{:x}", Sha256::digest(BODY));
+        assert_eq!(
+            std::fs::read(dir.join(format!("{hash}.mp4"))).unwrap(),
+            BODY
+        );
+        assert!(!dir.join("1-v0.part").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn skips_failed_downloads_and_foreign_json() {
+        const FILES: &[(&str, &[u8])] = &[("/media/a.jpg", b"image")];
+        let (base, _) = serve(FILES);
+        let dir = scratch("failures");
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("tweets.json"), r#"{"tweets": []}"#).unwrap();
+        std::fs::write(dir.join(format!("{}.jpg.json", "0".repeat(64))), "not json").unwrap();
+
+        let tweets = [
+            tweet("1", &[format!("{base}/media/missing.jpg")], &[]),
+            tweet("2", &[format!("{base}/media/a.jpg")], &[]),
+        ];
+        let mut downloader = Downloader::new(reqwest::Client::new(), &dir, 2).unwrap();
+        downloader.download(&tweets).await;
+
+        let summary = downloader.summary();
+        assert_eq!((summary.downloaded, summary.failed), (1, 1));
+
+        let hash = format!("{:x}", Sha256::digest(b"image"));
+        assert_eq!(
+            std::fs::read(dir.join(format!("{hash}.jpg"))).unwrap(),
+            b"image"
+        );
+        assert!(is_sidecar(&dir.join(format!("{hash}.jpg.json"))));
+        assert!(!is_sidecar(&dir.join("tweets.json")));
+        assert_eq!(index(&dir).unwrap().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
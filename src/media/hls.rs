@@ -0,0 +1,479 @@
+use std::{
+    collections::HashMap,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use futures::{StreamExt as _, TryStreamExt as _};
+use url::Url;
+
+#[derive(Debug, PartialEq)]
+struct Variant {
+    bandwidth: u64,
+    audio: Option<String>,
+    uri: Url,
+}
+
+#[derive(Debug, PartialEq)]
+struct Audio {
+    group: String,
+    default: bool,
+    uri: Option<Url>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Playlist {
+    Master {
+        variants: Vec<Variant>,
+        audio: Vec<Audio>,
+    },
+    Media {
+        init: Option<Url>,
+        segments: Vec<Url>,
+    },
+}
+
+fn parse(text: &str, base: &Url) -> anyhow::Result<Playlist> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    if lines.next() != Some("#EXTM3U") {
+        anyhow::bail!("not an HLS playlist");
+    }
+
+    let mut variants = Vec::new();
+    let mut audio = Vec::new();
+    let mut segments = Vec::new();
+    let mut init = None;
+    let mut stream = None;
+    let mut segment = false;
+
+    for line in lines {
+        if let Some(value) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+            let mut attributes = attributes(value);
+            let value = attributes
+                .get("BANDWIDTH")
+                .ok_or_else(|| anyhow::format_err!("variant without BANDWIDTH: `{line}`"))?;
+            let bandwidth = value
+                .parse::<u64>()
+                .with_context(|| format!("invalid BANDWIDTH `{value}`"))?;
+            stream = Some((bandwidth, attributes.remove("AUDIO")));
+        } else if let Some(value) = line.strip_prefix("#EXT-X-MEDIA:") {
+            let mut attributes = attributes(value);
+            if attributes.get("TYPE").map(String::as_str) != Some("AUDIO") {
+                continue;
+            }
+
+            let group = attributes
+                .remove("GROUP-ID")
+                .ok_or_else(|| anyhow::format_err!("EXT-X-MEDIA without GROUP-ID: `{line}`"))?;
+            let uri = attributes
+                .remove("URI")
+                .map(|uri| base.join(&uri))
+                .transpose()
+                .with_context(|| format!("invalid URI in `{line}`"))?;
+            audio.push(Audio {
+                group,
+                default: attributes.get("DEFAULT").map(String::as_str) == Some("YES"),
+                uri,
+            });
+        } else if line.starts_with("#EXTINF:") {
+            segment = true;
+        } else if let Some(value) = line.strip_prefix("#EXT-X-MAP:") {
+            let mut attributes = attributes(value);
+            if attributes.contains_key("BYTERANGE") {
+                anyhow::bail!("byte-range init segments (EXT-X-MAP BYTERANGE) are not supported");
+            }
+
+            let uri = attributes
+                .remove("URI")
+                .ok_or_else(|| anyhow::format_err!("EXT-X-MAP without URI: `{line}`"))?;
+            let uri = base
+                .join(&uri)
+                .with_context(|| format!("invalid URI in `{line}`"))?;
+            if init.as_ref().is_some_and(|init| init != &uri) {
+                anyhow::bail!("playlists that switch init segments (EXT-X-MAP) are not supported");
+            }
+            init = Some(uri);
+        } else if line.starts_with("#EXT-X-BYTERANGE:") {
+            anyhow::bail!("byte-range playlists (EXT-X-BYTERANGE) are not supported");
+        } else if let Some(value) = line.strip_prefix("#EXT-X-KEY:") {
+            let method = attributes(value).remove("METHOD").unwrap_or_default();
+            if method != "NONE" {
+                anyhow::bail!("encrypted HLS playlists ({method}) are not supported");
+            }
+        } else if !line.starts_with('#') {
+            let uri = base
+                .join(line)
+                .with_context(|| format!("invalid URI `{line}`"))?;
+
+            if let Some((bandwidth, audio)) = stream.take() {
+                variants.push(Variant {
+                    bandwidth,
+                    audio,
+                    uri,
+                });
+            } else if std::mem::take(&mut segment) {
+                segments.push(uri);
+            }
+        }
+    }
+
+    if !variants.is_empty() {
+        Ok(Playlist::Master { variants, audio })
+    } else if !segments.is_empty() {
+        Ok(Playlist::Media { init, segments })
+    } else {
+        anyhow::bail!("HLS playlist has no variants or segments")
+    }
+}
+
+fn attributes(value: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = value;
+
+    while let Some((name, tail)) = rest.split_once('=') {
+        let (value, tail) = match tail.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                let tail = quoted.get(end + 1..).unwrap_or_default();
+                (&quoted[..end], tail)
+            }
+            None => tail.split_once(',').unwrap_or((tail, "")),
+        };
+
+        attributes.insert(name.trim().to_owned(), value.to_owned());
+        rest = tail.trim_start_matches(',');
+    }
+
+    attributes
+}
+
+async fn playlist(client: &reqwest::Client, url: &Url) -> anyhow::Result<Playlist> {
+    let text = client
+        .get(url.clone())
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    parse(&text, url).with_context(|| format!("parsing `{url}`"))
+}
+
+async fn segments(client: &reqwest::Client, url: &Url) -> anyhow::Result<(Option<Url>, Vec<Url>)> {
+    match playlist(client, url).await? {
+        Playlist::Media { init, segments } => Ok((init, segments)),
+        Playlist::Master { .. } => {
+            anyhow::bail!("`{url}` is a master playlist, expected segments")
+        }
+    }
+}
+
+fn pick_audio<'a>(variant: &Variant, audio: &'a [Audio]) -> anyhow::Result<Option<&'a Url>> {
+    let Some(group) = &variant.audio else {
+        return Ok(None);
+    };
+
+    let mut renditions = audio.iter().filter(|audio| &audio.group == group);
+    let first = renditions.clone().next().ok_or_else(|| {
+        anyhow::format_err!(
+            "variant `{}` uses missing audio group `{group}`",
+            variant.uri
+        )
+    })?;
+    let chosen = renditions.find(|audio| audio.default).unwrap_or(first);
+
+    Ok(chosen.uri.as_ref())
+}
+
+fn pick_variant<'a>(variants: &'a [Variant], audio: &[Audio]) -> Option<&'a Variant> {
+    variants
+        .iter()
+        .filter(|variant| matches!(pick_audio(variant, audio), Ok(None)))
+        .max_by_key(|variant| variant.bandwidth)
+}
+
+pub(super) struct Track {
+    pub(super) part: PathBuf,
+    pub(super) extension: &'static str,
+}
+
+pub(super) async fn fetch(
+    client: &reqwest::Client,
+    dir: &Path,
+    url: &str,
+    media_id: &str,
+    concurrency: usize,
+) -> anyhow::Result<Track> {
+    let url = Url::parse(url)?;
+
+    let (init, uris) = match playlist(client, &url).await? {
+        Playlist::Media { init, segments } => (init, segments),
+        Playlist::Master { variants, audio } => {
+            let Some(best) = pick_variant(&variants, &audio) else {
+                anyhow::bail!(
+                    "every variant of `{url}` takes its audio from a separate rendition, \
+                     which would need a remux"
+                );
+            };
+
+            segments(client, &best.uri).await?
+        }
+    };
+
+    Ok(Track {
+        extension: if init.is_some() { "mp4" } else { "ts" },
+        part: join(client, dir, init.as_ref(), &uris, media_id, concurrency).await?,
+    })
+}
+
+async fn join(
+    client: &reqwest::Client,
+    dir: &Path,
+    init: Option<&Url>,
+    uris: &[Url],
+    name: &str,
+    concurrency: usize,
+) -> anyhow::Result<PathBuf> {
+    let segments = dir.join(format!("{name}.hls"));
+    std::fs::create_dir_all(&segments)
+        .with_context(|| format!("creating `{}`", segments.display()))?;
+
+    let files = init
+        .map(|uri| (segments.join("init"), uri))
+        .into_iter()
+        .chain(
+            uris.iter()
+                .enumerate()
+                .map(|(index, uri)| (segments.join(format!("{index:05}")), uri)),
+        )
+        .collect::<Vec<_>>();
+
+    futures::stream::iter(&files)
+        .map(|(path, uri)| segment(client, path, uri))
+        .buffer_unordered(concurrency.max(1))
+        .try_collect::<Vec<()>>()
+        .await?;
+
+    let part = dir.join(format!("{name}.part"));
+    let file =
+        std::fs::File::create(&part).with_context(|| format!("opening `{}`", part.display()))?;
+    let mut writer = std::io::BufWriter::new(file);
+    for (path, _) in &files {
+        let mut segment = std::fs::File::open(path)?;
+        std::io::copy(&mut segment, &mut writer)?;
+    }
+    writer.flush()?;
+
+    std::fs::remove_dir_all(&segments)?;
+
+    Ok(part)
+}
+
+async fn segment(client: &reqwest::Client, path: &Path, uri: &Url) -> anyhow::Result<()> {
+    if path.exists() {
+        return Ok(());
+    }
+
+    let bytes = client
+        .get(uri.clone())
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await
+        .with_context(|| format!("downloading segment `{uri}`"))?;
+
+    let partial = path.with_extension("part");
+    std::fs::write(&partial, bytes)
+        .and_then(|()| std::fs::rename(&partial, path))
+        .with_context(|| format!("writing `{}`", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://video.twimg.com/ext_tw_video/1/pu/pl/master.m3u8?tag=12").unwrap()
+    }
+
+    #[test]
+    fn parses_master_playlists() {
+        let text = r#"#EXTM3U
+#EXT-X-INDEPENDENT-SEGMENTS
+#EXT-X-MEDIA:NAME="Audio",TYPE=AUDIO,GROUP-ID="audio-128000",AUTOSELECT=YES,URI="/ext_tw_video/1/pu/pl/mp4a/128000/audio.m3u8"
+#EXT-X-STREAM-INF:AVERAGE-BANDWIDTH=251000,BANDWIDTH=256000,RESOLUTION=480x270,CODECS="mp4a.40.2,avc1.4d0015"
+/ext_tw_video/1/pu/pl/480x270/low.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=2176000,RESOLUTION=1280x720,CODECS="mp4a.40.2,avc1.640020",AUDIO="audio-128000"
+1280x720/high.m3u8
+"#;
+
+        assert_eq!(
+            parse(text, &base()).unwrap(),
+            Playlist::Master {
+                variants: vec![
+                    Variant {
+                        bandwidth: 256000,
+                        audio: None,
+                        uri: Url::parse(
+                            "https://video.twimg.com/ext_tw_video/1/pu/pl/480x270/low.m3u8"
+                        )
+                        .unwrap(),
+                    },
+                    Variant {
+                        bandwidth: 2176000,
+                        audio: Some("audio-128000".to_owned()),
+                        uri: Url::parse(
+                            "https://video.twimg.com/ext_tw_video/1/pu/pl/1280x720/high.m3u8"
+                        )
+                        .unwrap(),
+                    },
+                ],
+                audio: vec![Audio {
+                    group: "audio-128000".to_owned(),
+                    default: false,
+                    uri: Some(
+                        Url::parse(
+                            "https://video.twimg.com/ext_tw_video/1/pu/pl/mp4a/128000/audio.m3u8"
+                        )
+                        .unwrap()
+                    ),
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn parses_media_playlists() {
+        let text = r#"#EXTM3U
+#EXT-X-VERSION:3
+#EXT-X-TARGETDURATION:3
+#EXT-X-KEY:METHOD=NONE
+#EXTINF:3.000,
+0/3000/seg0.ts
+#EXTINF:1.500,
+https://cdn.example/seg1.ts
+#EXT-X-ENDLIST
+"#;
+
+        assert_eq!(
+            parse(text, &base()).unwrap(),
+            Playlist::Media {
+                init: None,
+                segments: vec![
+                    Url::parse("https://video.twimg.com/ext_tw_video/1/pu/pl/0/3000/seg0.ts")
+                        .unwrap(),
+                    Url::parse("https://cdn.example/seg1.ts").unwrap(),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn parses_fmp4_playlists_with_an_audio_group() {
+        let master = r#"#EXTM3U
+#EXT-X-VERSION:7
+#EXT-X-MEDIA:NAME="Audio",TYPE=AUDIO,GROUP-ID="audio-128000",DEFAULT=YES,URI="/ext_tw_video/1/pu/pl/mp4a/128000/audio.m3u8"
+#EXT-X-MEDIA:NAME="Main",TYPE=AUDIO,GROUP-ID="muxed",DEFAULT=YES
+#EXT-X-STREAM-INF:BANDWIDTH=2176000,CODECS="avc1.640020",AUDIO="audio-128000"
+/ext_tw_video/1/pu/pl/avc1/1280x720/high.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=832000,CODECS="mp4a.40.2,avc1.4d001f",AUDIO="muxed"
+/ext_tw_video/1/pu/pl/avc1/640x360/mid.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=256000,CODECS="mp4a.40.2,avc1.4d0015"
+/ext_tw_video/1/pu/pl/avc1/480x270/low.m3u8
+"#;
+        let Playlist::Master { variants, audio } = parse(master, &base()).unwrap() else {
+            panic!("expected a master playlist");
+        };
+        assert_eq!(variants.len(), 3);
+        assert_eq!(
+            audio[0].uri.as_ref().map(Url::as_str),
+            Some("https://video.twimg.com/ext_tw_video/1/pu/pl/mp4a/128000/audio.m3u8")
+        );
+        assert_eq!(audio[1].uri, None);
+        assert_eq!(
+            pick_variant(&variants, &audio).map(|variant| variant.uri.as_str()),
+            Some("https://video.twimg.com/ext_tw_video/1/pu/pl/avc1/640x360/mid.m3u8")
+        );
+        assert_eq!(pick_variant(&variants[..1], &audio), None);
+
+        let media = r#"#EXTM3U
+#EXT-X-VERSION:7
+#EXT-X-TARGETDURATION:3
+#EXT-X-MAP:URI="/ext_tw_video/1/pu/vid/avc1/0/0/640x360/init.mp4"
+#EXTINF:3.000,
+/ext_tw_video/1/pu/vid/avc1/0/3000/640x360/0.m4s
+#EXTINF:1.500,
+/ext_tw_video/1/pu/vid/avc1/3000/4500/640x360/1.m4s
+#EXT-X-ENDLIST
+"#;
+        let uri = |path: &str| Url::parse(&format!("https://video.twimg.com{path}")).unwrap();
+        assert_eq!(
+            parse(media, &base()).unwrap(),
+            Playlist::Media {
+                init: Some(uri("/ext_tw_video/1/pu/vid/avc1/0/0/640x360/init.mp4")),
+                segments: vec![
+                    uri("/ext_tw_video/1/pu/vid/avc1/0/3000/640x360/0.m4s"),
+                    uri("/ext_tw_video/1/pu/vid/avc1/3000/4500/640x360/1.m4s"),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn picks_the_default_rendition_of_the_variant_audio_group() {
+        let uri = |path: &str| Url::parse(&format!("https://video.twimg.com/{path}")).unwrap();
+        let audio = |group: &str, default: bool, path: Option<&str>| Audio {
+            group: group.to_owned(),
+            default,
+            uri: path.map(uri),
+        };
+        let variant = |group: Option<&str>| Variant {
+            bandwidth: 1,
+            audio: group.map(ToOwned::to_owned),
+            uri: uri("video.m3u8"),
+        };
+        let groups = [
+            audio("low", false, Some("low.m3u8")),
+            audio("high", false, Some("en.m3u8")),
+            audio("high", true, Some("es.m3u8")),
+            audio("muxed", true, None),
+        ];
+
+        assert_eq!(pick_audio(&variant(None), &groups).unwrap(), None);
+        assert_eq!(
+            pick_audio(&variant(Some("low")), &groups).unwrap(),
+            Some(&uri("low.m3u8"))
+        );
+        assert_eq!(
+            pick_audio(&variant(Some("high")), &groups).unwrap(),
+            Some(&uri("es.m3u8"))
+        );
+        assert_eq!(pick_audio(&variant(Some("muxed")), &groups).unwrap(), None);
+        assert!(pick_audio(&variant(Some("missing")), &groups).is_err());
+    }
+
+    #[test]
+    fn rejects_unsupported_playlists() {
+        let error = |text: &str| parse(text, &base()).unwrap_err().to_string();
+
+        assert_eq!(error("<html></html>"), "not an HLS playlist");
+        assert_eq!(
+            error("#EXTM3U\n#EXT-X-ENDLIST\n"),
+            "HLS playlist has no variants or segments"
+        );
+        assert!(
+            error("#EXTM3U\n#EXT-X-KEY:METHOD=AES-128,URI=\"key\"\n#EXTINF:1,\nseg.ts\n")
+                .starts_with("encrypted")
+        );
+        assert!(error(
+            "#EXTM3U\n#EXT-X-MAP:URI=\"a.mp4\"\n#EXTINF:1,\n0.m4s\n#EXT-X-MAP:URI=\"b.mp4\"\n#EXTINF:1,\n1.m4s\n"
+        )
+        .contains("EXT-X-MAP"));
+        assert!(
+            error("#EXTM3U\n#EXTINF:1,\n#EXT-X-BYTERANGE:1000@0\nall.ts\n")
+                .contains("EXT-X-BYTERANGE")
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    sync::LazyLock,
+};
+
+use anyhow::Context as _;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+static BUNDLE_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"https://abs\.twimg\.com/responsive-web/client-web(?:-legacy)?/main\.[0-9a-f]+[a-z]?\.js",
+    )
+    .unwrap()
+});
+
+static OPERATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"queryId:"([^"]+)",operationName:"([^"]+)",operationType:"[^"]+",metadata:\{featureSwitches:\[([^\]]*)\],fieldToggles:\[([^\]]*)\]"#,
+    )
+    .unwrap()
+});
+
+static FEATURE_VALUE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""([a-z0-9_]+)":\{"value":(true|false)\}"#).unwrap());
+
+static STRING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#""([^"]+)""#).unwrap());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Operation {
+    #[serde(default)]
+    pub(crate) query_id: String,
+    #[serde(default)]
+    pub(crate) features: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub(crate) field_toggles: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Operation {
+    fn merge(&mut self, other: Self) {
+        if !other.query_id.is_empty() {
+            self.query_id = other.query_id;
+        }
+
+        if !other.features.is_empty() {
+            self.features = other.features;
+        }
+
+        if !other.field_toggles.is_empty() {
+            self.field_toggles = other.field_toggles;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub(crate) struct Registry {
+    operations: BTreeMap<String, Operation>,
+}
+
+impl Registry {
+    pub(crate) fn builtin() -> Self {
+        serde_json::from_str(include_str!("operations/default.json"))
+            .expect("built-in operations are valid")
+    }
+
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading `{}`", path.display()))?;
+
+        let registry = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::from_str(&text)?,
+            _ => toml::from_str(&text)?,
+        };
+
+        Ok(registry)
+    }
+
+    pub(crate) fn merge(&mut self, other: Self) {
+        for (name, operation) in other.operations {
+            self.operations.entry(name).or_default().merge(operation);
+        }
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let text = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::to_string_pretty(self)?,
+            _ => toml::to_string_pretty(self)?,
+        };
+
+        std::fs::write(path, text).with_context(|| format!("writing `{}`", path.display()))
+    }
+
+    pub(crate) fn replace(&mut self, other: Self) {
+        self.operations.extend(other.operations);
+    }
+
+    pub(crate) fn refresh(
+        &self,
+        discovered: Vec<DiscoveredOperation>,
+        feature_values: &HashMap<String, bool>,
+    ) -> Self {
+        let mut refreshed = Self::default();
+
+        for operation in discovered {
+            let existing = self
+                .operations
+                .get(&operation.name)
+                .cloned()
+                .unwrap_or_default();
+            let value = |known: &serde_json::Map<_, _>, name: &String| {
+                feature_values
+                    .get(name)
+                    .map(|&value| serde_json::Value::Bool(value))
+                    .or_else(|| known.get(name).cloned())
+                    .unwrap_or(serde_json::Value::Bool(false))
+            };
+
+            let entry = Operation {
+                query_id: operation.query_id,
+                features: operation
+                    .features
+                    .iter()
+                    .map(|name| (name.clone(), value(&existing.features, name)))
+                    .collect(),
+                field_toggles: operation
+                    .field_toggles
+                    .iter()
+                    .map(|name| (name.clone(), value(&existing.field_toggles, name)))
+                    .collect(),
+            };
+
+            refreshed.operations.insert(operation.name, entry);
+        }
+
+        refreshed
+    }
+
+    pub(crate) fn get(&self, name: &str) -> anyhow::Result<&Operation> {
+        self.operations
+            .get(name)
+            .filter(|operation| !operation.query_id.is_empty())
+            .ok_or_else(|| anyhow::format_err!("no query id known for operation `{name}`"))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct DiscoveredOperation {
+    pub(crate) name: String,
+    pub(crate) query_id: String,
+    pub(crate) features: Vec<String>,
+    pub(crate) field_toggles: Vec<String>,
+}
+
+pub(crate) fn parse_bundle_url(html: &str) -> Option<&str> {
+    BUNDLE_URL.find(html).map(|found| found.as_str())
+}
+
+pub(crate) fn parse_bundle(js: &str) -> Vec<DiscoveredOperation> {
+    let strings = |list: &str| {
+        STRING
+            .captures_iter(list)
+            .map(|captures| captures[1].to_owned())
+            .collect()
+    };
+
+    OPERATION
+        .captures_iter(js)
+        .map(|captures| DiscoveredOperation {
+            query_id: captures[1].to_owned(),
+            name: captures[2].to_owned(),
+            features: strings(&captures[3]),
+            field_toggles: strings(&captures[4]),
+        })
+        .collect()
+}
+
+pub(crate) fn parse_feature_values(html: &str) -> HashMap<String, bool> {
+    FEATURE_VALUE
+        .captures_iter(html)
+        .map(|captures| (captures[1].to_owned(), &captures[2] == "true"))
+        .collect()
+}
+
+pub(crate) async fn discover(
+    client: &reqwest::Client,
+    bundle: Option<&str>,
+) -> anyhow::Result<(Vec<DiscoveredOperation>, HashMap<String, bool>)> {
+    let fetch = |url: &str| {
+        let request = client.get(url);
+        async move {
+            request
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await
+                .map_err(anyhow::Error::from)
+        }
+    };
+
+    let (js, feature_values) = match bundle {
+        Some(url) if url.starts_with("https://") || url.starts_with("http://") => {
+            (fetch(url).await?, HashMap::new())
+        }
+        Some(path) => (
+            std::fs::read_to_string(path).with_context(|| format!("reading `{path}`"))?,
+            HashMap::new(),
+        ),
+        None => {
+            let home = fetch("https://x.com").await?;
+            let url = parse_bundle_url(&home)
+                .ok_or_else(|| anyhow::format_err!("web client bundle not found on x.com"))?;
+
+            (fetch(url).await?, parse_feature_values(&home))
+        }
+    };
+
+    let operations = parse_bundle(&js);
+    if operations.is_empty() {
+        anyhow::bail!("no GraphQL operations found in the bundle");
+    }
+
+    Ok((operations, feature_values))
+}
+
+pub(crate) fn overrides(path: &Path) -> anyhow::Result<Registry> {
+    if !path.exists() {
+        return Ok(Registry::default());
+    }
+
+    Registry::load(path)
+        .with_context(|| format!("loading operation overrides from `{}`", path.display()))
+}
+
+pub(crate) fn from_config(config: &Config) -> anyhow::Result<Registry> {
+    let path = config.operations_path();
+    let overrides = match &config.operations {
+        Some(_) => Registry::load(&path)
+            .with_context(|| format!("loading operation overrides from `{}`", path.display()))?,
+        None => overrides(&path)?,
+    };
+
+    let mut registry = Registry::builtin();
+    registry.merge(overrides);
+
+    Ok(registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUNDLE: &str = include_str!("operations/fixtures/main.js");
+    const HOME: &str = include_str!("operations/fixtures/home.html");
+
+    #[test]
+    fn finds_bundle_url() {
+        assert_eq!(
+            parse_bundle_url(HOME),
+            Some("https://abs.twimg.com/responsive-web/client-web/main.5c1b3bd3.js")
+        );
+    }
+
+    #[test]
+    fn extracts_operations() {
+        let operations = parse_bundle(BUNDLE);
+
+        assert_eq!(operations.len(), 3);
+        assert_eq!(
+            operations[0],
+            DiscoveredOperation {
+                name: "SearchTimeline".to_owned(),
+                query_id: "UN1i3zUiCWa-6r-Uaho4fw".to_owned(),
+                features: vec![
+                    "rweb_video_screen_enabled".to_owned(),
+                    "profile_label_improvements_pcf_label_in_post_enabled".to_owned(),
+                    "responsive_web_graphql_timeline_navigation_enabled".to_owned(),
+                    "view_counts_everywhere_api_enabled".to_owned(),
+                    "longform_notetweets_consumption_enabled".to_owned(),
+                ],
+                field_toggles: vec![
+                    "withArticleRichContentState".to_owned(),
+                    "withArticlePlainText".to_owned(),
+                ],
+            }
+        );
+        assert_eq!(operations[2].name, "CreateBookmark");
+        assert!(operations[2].features.is_empty());
+    }
+
+    #[test]
+    fn refresh_prefers_page_values_then_known_values() {
+        let registry =
+            Registry::builtin().refresh(parse_bundle(BUNDLE), &parse_feature_values(HOME));
+
+        let search = registry.get("SearchTimeline").unwrap();
+        assert_eq!(search.query_id, "UN1i3zUiCWa-6r-Uaho4fw");
+        assert_eq!(search.features.len(), 5);
+        assert_eq!(search.features["rweb_video_screen_enabled"], false);
+        assert_eq!(
+            search.features["longform_notetweets_consumption_enabled"],
+            true
+        );
+        assert_eq!(
+            search.features["profile_label_improvements_pcf_label_in_post_enabled"],
+            false
+        );
+        assert_eq!(search.field_toggles["withArticleRichContentState"], false);
+
+        let user = registry.get("UserByScreenName").unwrap();
+        assert_eq!(user.features["hidden_profile_subscriptions_enabled"], true);
+    }
+
+    #[test]
+    fn refresh_keeps_only_discovered_operations() {
+        let discovered = parse_bundle(BUNDLE)
+            .into_iter()
+            .filter(|operation| operation.name != "SearchTimeline")
+            .collect();
+        let refreshed = Registry::builtin().refresh(discovered, &HashMap::new());
+
+        assert_eq!(
+            refreshed.operations.keys().collect::<Vec<_>>(),
+            ["CreateBookmark", "UserByScreenName"]
+        );
+
+        let mut overrides = Registry::default();
+        overrides.operations.insert(
+            "UserByScreenName".to_owned(),
+            Operation {
+                query_id: "stale".to_owned(),
+                ..Operation::default()
+            },
+        );
+        overrides.replace(refreshed);
+        assert_ne!(overrides.get("UserByScreenName").unwrap().query_id, "stale");
+    }
+
+    #[test]
+    fn overrides_replace_the_builtin_flags() {
+        let refreshed = Registry::builtin().refresh(parse_bundle(BUNDLE), &HashMap::new());
+        let builtin = Registry::builtin();
+        let dropped = builtin
+            .get("SearchTimeline")
+            .unwrap()
+            .features
+            .keys()
+            .find(|name| {
+                !refreshed.operations["SearchTimeline"]
+                    .features
+                    .contains_key(*name)
+            })
+            .expect("the fixture bundle drops a built-in flag")
+            .clone();
+
+        let mut registry = Registry::builtin();
+        registry.merge(refreshed);
+
+        let search = registry.get("SearchTimeline").unwrap();
+        assert!(
+            !search.features.contains_key(&dropped),
+            "{dropped} is still sent"
+        );
+        assert_eq!(search.features.len(), 5);
+        assert_eq!(search.field_toggles.len(), 2);
+
+        let mut partial = Registry::default();
+        partial.operations.insert(
+            "SearchTimeline".to_owned(),
+            Operation {
+                query_id: "new".to_owned(),
+                ..Operation::default()
+            },
+        );
+        let mut registry = Registry::builtin();
+        registry.merge(partial);
+        assert_eq!(
+            registry.get("SearchTimeline").unwrap().features,
+            builtin.get("SearchTimeline").unwrap().features
+        );
+    }
+}
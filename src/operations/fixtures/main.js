@@ -0,0 +1 @@
+(self.webpackChunk_twitter_responsive_web=self.webpackChunk_twitter_responsive_web||[]).push([["main"],{10187:e=>{e.exports={queryId:"UN1i3zUiCWa-6r-Uaho4fw",operationName:"SearchTimeline",operationType:"query",metadata:{featureSwitches:["rweb_video_screen_enabled","profile_label_improvements_pcf_label_in_post_enabled","responsive_web_graphql_timeline_navigation_enabled","view_counts_everywhere_api_enabled","longform_notetweets_consumption_enabled"],fieldToggles:["withArticleRichContentState","withArticlePlainText"]}}},45513:e=>{e.exports={queryId:"xmU6X_CKVnQ5lSrCbAmJsg",operationName:"UserByScreenName",operationType:"query",metadata:{featureSwitches:["hidden_profile_subscriptions_enabled","responsive_web_graphql_timeline_navigation_enabled"],fieldToggles:["withAuxiliaryUserLabels"]}}},86502:e=>{e.exports={queryId:"pjFnHGVqCjTcZol0xcBJjw",operationName:"CreateBookmark",operationType:"mutation",metadata:{featureSwitches:[],fieldToggles:[]}}},55421:(e,t,n)=>{"use strict";n.d(t,{Z:()=>r});const r=n(11098).Z}}]);
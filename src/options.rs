@@ -1,15 +1,31 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use anyhow::Context as _;
+use chrono::{DateTime, NaiveDate, Utc};
 use clap::Parser;
 
-use crate::scraper::SearchMode;
+use crate::{
+    query::{Filter, SearchQuery, Term},
+    scraper::SearchMode,
+};
 
 #[derive(Parser)]
-pub(crate) enum Options {
+pub(crate) struct Options {
+    #[clap(long, global = true)]
+    pub(crate) config: Option<PathBuf>,
+    #[clap(long, global = true)]
+    pub(crate) profile: Option<String>,
+    #[command(subcommand)]
+    pub(crate) command: Command,
+}
+
+#[derive(clap::Subcommand)]
+pub(crate) enum Command {
     Tweets {
-        query: String,
+        #[command(flatten)]
+        query: Box<QueryArgs>,
         #[clap(long)]
-        from: Option<String>,
-        #[clap(long, default_value_t = SearchMode::Top)]
-        search_mode: SearchMode,
+        search_mode: Option<SearchMode>,
         #[clap(long, default_value_t = 50)]
         count: u32,
         #[clap(long)]
@@ -17,7 +33,35 @@ pub(crate) enum Options {
         #[clap(long, default_value_t = Output::PrettyPrint)]
         output: Output,
         #[clap(long)]
+        out: Option<PathBuf>,
+        #[clap(long, value_delimiter = ',')]
+        columns: Vec<String>,
+        #[clap(long)]
         all: bool,
+        #[clap(long, default_value_t = 3)]
+        max_empty_pages: u32,
+        #[clap(long, requires = "all")]
+        checkpoint: Option<PathBuf>,
+        #[clap(long, requires = "checkpoint")]
+        resume: bool,
+        #[clap(long, requires = "all")]
+        limit: Option<usize>,
+        #[clap(long, value_parser = parse_date)]
+        since: Option<DateTime<Utc>>,
+        #[clap(long, value_parser = parse_date)]
+        until: Option<DateTime<Utc>>,
+        #[clap(long, requires = "all")]
+        max_duration: Option<humantime::Duration>,
+        #[clap(long, conflicts_with = "all")]
+        shards: Option<usize>,
+        #[clap(long, default_value_t = 4)]
+        concurrency: usize,
+        #[clap(long, default_value_t = 1000)]
+        shard_cap: usize,
+        #[clap(long)]
+        download_media: Option<PathBuf>,
+        #[clap(long, default_value_t = 4)]
+        media_concurrency: usize,
     },
     Profiles {
         query: String,
@@ -28,17 +72,281 @@ pub(crate) enum Options {
         #[clap(long, default_value_t = Output::PrettyPrint)]
         output: Output,
         #[clap(long)]
+        out: Option<PathBuf>,
+        #[clap(long, value_delimiter = ',')]
+        columns: Vec<String>,
+        #[clap(long)]
         all: bool,
+        #[clap(long, default_value_t = 3)]
+        max_empty_pages: u32,
+        #[clap(long, requires = "all")]
+        checkpoint: Option<PathBuf>,
+        #[clap(long, requires = "checkpoint")]
+        resume: bool,
+        #[clap(long, requires = "all")]
+        limit: Option<usize>,
+        #[clap(long, requires = "all")]
+        max_duration: Option<humantime::Duration>,
     },
+    Auth {
+        #[command(subcommand)]
+        command: AuthCommand,
+    },
+    Ops {
+        #[command(subcommand)]
+        command: OpsCommand,
+    },
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+    Media {
+        input: PathBuf,
+        #[clap(long)]
+        dir: PathBuf,
+        #[clap(long, default_value_t = 4)]
+        concurrency: usize,
+    },
+}
+
+#[derive(clap::Args)]
+pub(crate) struct QueryArgs {
+    query: Option<String>,
+    #[clap(long)]
+    from: Vec<String>,
+    #[clap(long)]
+    to: Option<String>,
+    #[clap(long)]
+    mention: Vec<String>,
+    #[clap(long)]
+    min_faves: Option<u32>,
+    #[clap(long)]
+    min_retweets: Option<u32>,
+    #[clap(long)]
+    min_replies: Option<u32>,
+    #[clap(long)]
+    lang: Option<String>,
+    #[clap(long)]
+    filter: Vec<Filter>,
+    #[clap(long)]
+    exclude_filter: Vec<Filter>,
+    #[clap(long)]
+    url: Option<String>,
+    #[clap(long)]
+    conversation_id: Option<String>,
+    #[clap(long)]
+    near: Option<String>,
+    #[clap(long, requires = "near")]
+    within: Option<String>,
+    #[clap(long)]
+    phrase: Vec<String>,
+    #[clap(long)]
+    any: Vec<String>,
+    #[clap(long)]
+    exclude: Vec<String>,
 }
 
-#[derive(Debug, Clone, strum::EnumString, strum::Display)]
-#[strum(serialize_all = "snake_case")]
+impl QueryArgs {
+    pub(crate) fn build(self) -> anyhow::Result<SearchQuery> {
+        let mut query = match self.query.as_deref().filter(|text| !text.trim().is_empty()) {
+            Some(text) => text
+                .parse::<SearchQuery>()
+                .with_context(|| format!("invalid search query `{text}`"))?,
+            None => SearchQuery::new(),
+        };
+
+        for phrase in self.phrase {
+            query = query.phrase(phrase);
+        }
+
+        if !self.any.is_empty() {
+            query = query.any(self.any.into_iter().map(Term::Word));
+        }
+
+        for word in self.exclude {
+            query = query.exclude(Term::Word(word));
+        }
+
+        query = query.authors(self.from.iter().map(String::as_str));
+
+        if let Some(user) = &self.to {
+            query = query.to(user);
+        }
+
+        for user in &self.mention {
+            query = query.mention(user);
+        }
+
+        if let Some(count) = self.min_faves {
+            query = query.min_faves(count);
+        }
+
+        if let Some(count) = self.min_retweets {
+            query = query.min_retweets(count);
+        }
+
+        if let Some(count) = self.min_replies {
+            query = query.min_replies(count);
+        }
+
+        if let Some(lang) = self.lang {
+            query = query.lang(lang);
+        }
+
+        for filter in self.filter {
+            query = query.filter(filter);
+        }
+
+        for filter in self.exclude_filter {
+            query = query.exclude_filter(filter);
+        }
+
+        if let Some(url) = self.url {
+            query = query.url(url);
+        }
+
+        if let Some(id) = self.conversation_id {
+            query = query.conversation_id(id);
+        }
+
+        if let Some(place) = self.near {
+            query = query.near(place);
+        }
+
+        if let Some(radius) = self.within {
+            query = query.within(radius);
+        }
+
+        Ok(query)
+    }
+}
+
+#[derive(clap::Subcommand)]
+pub(crate) enum AuthCommand {
+    Check,
+    Login,
+    Logout,
+}
+
+#[derive(Debug, Clone)]
 pub(crate) enum Output {
     PrettyPrint,
     Json,
+    Jsonl,
+    Csv,
+    Parquet,
+    Html,
+    Sqlite(PathBuf),
+    Postgres(String),
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "pretty_print" => Self::PrettyPrint,
+            "json" => Self::Json,
+            "jsonl" => Self::Jsonl,
+            "csv" => Self::Csv,
+            "parquet" => Self::Parquet,
+            "html" => Self::Html,
+            _ if value.starts_with("postgres://") || value.starts_with("postgresql://") => {
+                Self::Postgres(value.to_owned())
+            }
+            _ => match value.strip_prefix("sqlite:") {
+                Some(path) if !path.is_empty() => Self::Sqlite(path.into()),
+                _ => {
+                    return Err(format!(
+                        "expected one of pretty_print, json, jsonl, csv, parquet, html, sqlite:<path> or postgres://<url>, got `{value}`"
+                    ))
+                }
+            },
+        })
+    }
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PrettyPrint => f.write_str("pretty_print"),
+            Self::Json => f.write_str("json"),
+            Self::Jsonl => f.write_str("jsonl"),
+            Self::Csv => f.write_str("csv"),
+            Self::Parquet => f.write_str("parquet"),
+            Self::Html => f.write_str("html"),
+            Self::Sqlite(path) => write!(f, "sqlite:{}", path.display()),
+            Self::Postgres(url) => f.write_str(url),
+        }
+    }
 }
 
 pub(crate) fn from_args() -> Options {
     Options::parse()
 }
+
+#[derive(clap::Subcommand)]
+pub(crate) enum OpsCommand {
+    Refresh {
+        #[clap(long)]
+        bundle: Option<String>,
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub(crate) enum DbCommand {
+    Migrate { url: String },
+}
+
+fn parse_date(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(Default::default()).and_utc());
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| format!("expected `YYYY-MM-DD` or an RFC 3339 timestamp, got `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(args: &[&str]) -> String {
+        let options =
+            Options::try_parse_from(["twitter-scraper", "tweets"].iter().chain(args)).unwrap();
+        let Command::Tweets { query, .. } = options.command else {
+            unreachable!("parsed a tweets command");
+        };
+
+        query.build().unwrap().to_string()
+    }
+
+    #[test]
+    fn builds_queries_from_flags() {
+        assert_eq!(
+            query(&["rust", "--from", "@rustlang", "--min-faves", "5"]),
+            "rust from:rustlang min_faves:5"
+        );
+        assert_eq!(
+            query(&["--from", "@a", "--from", "b", "--exclude-filter", "replies"]),
+            "(from:a OR from:b) -filter:replies"
+        );
+        assert_eq!(
+            query(&[
+                "--any",
+                "rust",
+                "--any",
+                "go",
+                "--exclude",
+                "java",
+                "--phrase",
+                "type system"
+            ]),
+            "\"type system\" (rust OR go) -java"
+        );
+        assert_eq!(query(&["", "--from", "rustlang"]), "from:rustlang");
+    }
+}
@@ -0,0 +1,758 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+#[cfg(feature = "postgres")]
+use futures::future::BoxFuture;
+use serde::{de::DeserializeOwned, ser::SerializeMap as _, Serialize};
+
+use crate::{
+    options::Output,
+    pp,
+    scraper::SearchMode,
+    types::{self, profile::Profile, tweets::Tweet, Entry},
+};
+
+#[cfg(feature = "parquet")]
+mod columnar;
+mod columns;
+mod html;
+#[cfg(feature = "postgres")]
+pub(crate) mod postgres;
+mod sqlite;
+
+#[cfg(feature = "parquet")]
+const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+pub(crate) trait Record: Entry + Serialize + DeserializeOwned {
+    const KIND: &'static str;
+    const NAME: &'static str;
+    const COLUMNS: &'static [&'static str];
+
+    fn pretty_print(&self);
+
+    fn column(&self, column: &str) -> String;
+
+    fn store(store: &mut sqlite::Store, items: &[Self]) -> anyhow::Result<()>
+    where
+        Self: Sized;
+
+    fn archive(dir: &Path, items: &[Self], profile: Option<&Profile>) -> anyhow::Result<()>
+    where
+        Self: Sized;
+
+    #[cfg(feature = "postgres")]
+    fn upsert<'a>(
+        store: &'a mut postgres::Store,
+        items: &'a [Self],
+    ) -> BoxFuture<'a, anyhow::Result<()>>
+    where
+        Self: Sized;
+
+    #[cfg(feature = "parquet")]
+    fn schema() -> arrow_schema::SchemaRef;
+
+    #[cfg(feature = "parquet")]
+    fn batch(items: &[Self]) -> anyhow::Result<arrow_array::RecordBatch>
+    where
+        Self: Sized;
+}
+
+impl Record for Tweet {
+    const KIND: &'static str = "tweets";
+    const NAME: &'static str = "Tweet";
+    const COLUMNS: &'static [&'static str] = columns::TWEET;
+
+    fn pretty_print(&self) {
+        pp::tweet(self);
+    }
+
+    fn column(&self, column: &str) -> String {
+        columns::tweet(self, column)
+    }
+
+    fn store(store: &mut sqlite::Store, items: &[Self]) -> anyhow::Result<()> {
+        store.tweets(items)
+    }
+
+    fn archive(dir: &Path, items: &[Self], profile: Option<&Profile>) -> anyhow::Result<()> {
+        html::render(dir, items, profile)
+    }
+
+    #[cfg(feature = "postgres")]
+    fn upsert<'a>(
+        store: &'a mut postgres::Store,
+        items: &'a [Self],
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(store.tweets(items))
+    }
+
+    #[cfg(feature = "parquet")]
+    fn schema() -> arrow_schema::SchemaRef {
+        columnar::tweet_schema()
+    }
+
+    #[cfg(feature = "parquet")]
+    fn batch(items: &[Self]) -> anyhow::Result<arrow_array::RecordBatch> {
+        columnar::tweet_batch(items)
+    }
+}
+
+impl Record for Profile {
+    const KIND: &'static str = "profiles";
+    const NAME: &'static str = "Profile";
+    const COLUMNS: &'static [&'static str] = columns::PROFILE;
+
+    fn pretty_print(&self) {
+        pp::profile(self);
+    }
+
+    fn column(&self, column: &str) -> String {
+        columns::profile(self, column)
+    }
+
+    fn store(store: &mut sqlite::Store, items: &[Self]) -> anyhow::Result<()> {
+        store.profiles(items)
+    }
+
+    fn archive(_: &Path, _: &[Self], _: Option<&Profile>) -> anyhow::Result<()> {
+        anyhow::bail!("html output is only available for tweets")
+    }
+
+    #[cfg(feature = "postgres")]
+    fn upsert<'a>(
+        store: &'a mut postgres::Store,
+        items: &'a [Self],
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(store.profiles(items))
+    }
+
+    #[cfg(feature = "parquet")]
+    fn schema() -> arrow_schema::SchemaRef {
+        columnar::profile_schema()
+    }
+
+    #[cfg(feature = "parquet")]
+    fn batch(items: &[Self]) -> anyhow::Result<arrow_array::RecordBatch> {
+        columnar::profile_batch(items)
+    }
+}
+
+pub(crate) struct SinkOptions<'a> {
+    pub(crate) output: &'a Output,
+    pub(crate) out: Option<&'a Path>,
+    pub(crate) checkpoint: bool,
+    pub(crate) resume: bool,
+    pub(crate) committed: Option<u64>,
+    pub(crate) columns: &'a [String],
+    pub(crate) query: &'a str,
+    pub(crate) search_mode: SearchMode,
+    pub(crate) profile: Option<&'a Profile>,
+}
+
+pub(crate) enum Sink<T> {
+    PrettyPrint {
+        index: usize,
+    },
+    Json {
+        path: Option<PathBuf>,
+        items: Vec<T>,
+        next: Option<String>,
+        duplicates: usize,
+    },
+    JsonLines {
+        path: Option<PathBuf>,
+        writer: Box<dyn Write + Send>,
+    },
+    Csv {
+        path: Option<PathBuf>,
+        writer: Box<csv::Writer<Box<dyn Write + Send>>>,
+        columns: Vec<&'static str>,
+    },
+    #[cfg(feature = "parquet")]
+    Parquet {
+        writer: Box<parquet::arrow::ArrowWriter<Box<dyn Write + Send>>>,
+    },
+    Html {
+        dir: PathBuf,
+        items: Vec<T>,
+        profile: Option<Profile>,
+        duplicates: usize,
+    },
+    Sqlite {
+        store: sqlite::Store,
+    },
+    #[cfg(feature = "postgres")]
+    Postgres {
+        store: postgres::Store,
+    },
+}
+
+impl<T: Record> Sink<T> {
+    pub(crate) async fn open(options: &SinkOptions<'_>) -> anyhow::Result<Self> {
+        let &SinkOptions {
+            output,
+            out,
+            checkpoint,
+            resume,
+            committed,
+            columns,
+            ..
+        } = options;
+        let stdout = out.is_some_and(|path| path == Path::new("-"));
+
+        if !columns.is_empty() && !matches!(output, Output::Csv) {
+            anyhow::bail!("--columns can only be used with csv output");
+        }
+
+        if checkpoint && matches!(output, Output::Json | Output::Parquet | Output::Html) {
+            anyhow::bail!(
+                "--checkpoint cannot be used with {output} output, which is only complete once the crawl finishes; use jsonl, csv, sqlite or postgres instead"
+            );
+        }
+
+        Ok(match output {
+            Output::PrettyPrint if out.is_some() => {
+                anyhow::bail!("--out cannot be used with pretty_print output")
+            }
+            Output::PrettyPrint => Self::PrettyPrint { index: 0 },
+            Output::Json => {
+                let path = match out {
+                    _ if stdout => None,
+                    Some(path) => Some(path.to_owned()),
+                    None => Some(format!("{}.json", T::KIND).into()),
+                };
+                Self::Json {
+                    path,
+                    items: Vec::new(),
+                    next: None,
+                    duplicates: 0,
+                }
+            }
+            Output::Jsonl => {
+                let path = out.filter(|_| !stdout);
+                if resume {
+                    rewind(path, committed)?;
+                }
+
+                Self::JsonLines {
+                    writer: writer(path, resume)?,
+                    path: path.map(ToOwned::to_owned),
+                }
+            }
+            Output::Csv => {
+                let path = out.filter(|_| !stdout);
+                let columns = select_columns::<T>(columns)?;
+                if resume {
+                    rewind(path, committed)?;
+                }
+                let header = !(resume && path.is_some_and(|path| path.exists()));
+
+                let mut writer = csv::Writer::from_writer(writer(path, resume)?);
+                if header {
+                    writer.write_record(&columns)?;
+                }
+
+                Self::Csv {
+                    path: path.map(ToOwned::to_owned),
+                    writer: Box::new(writer),
+                    columns,
+                }
+            }
+            #[cfg(feature = "parquet")]
+            Output::Parquet => {
+                let path = match out {
+                    _ if stdout => None,
+                    Some(path) => Some(path.to_owned()),
+                    None => Some(PathBuf::from(format!("{}.parquet", T::KIND))),
+                };
+
+                let properties = parquet::file::properties::WriterProperties::builder()
+                    .set_compression(parquet::basic::Compression::SNAPPY)
+                    .set_max_row_group_size(ROW_GROUP_SIZE)
+                    .build();
+                let writer = parquet::arrow::ArrowWriter::try_new(
+                    writer(path.as_deref(), false)?,
+                    T::schema(),
+                    Some(properties),
+                )?;
+
+                Self::Parquet {
+                    writer: Box::new(writer),
+                }
+            }
+            #[cfg(not(feature = "parquet"))]
+            Output::Parquet => {
+                anyhow::bail!("parquet output needs a build with the `parquet` feature")
+            }
+            Output::Html if T::KIND != Tweet::KIND => {
+                anyhow::bail!("html output is only available for tweets")
+            }
+            Output::Html => Self::Html {
+                dir: html_dir(out)?,
+                items: Vec::new(),
+                profile: options.profile.cloned(),
+                duplicates: 0,
+            },
+            Output::Sqlite(_) if out.is_some() => {
+                anyhow::bail!("--out cannot be used with sqlite output, pass sqlite:<path> instead")
+            }
+            Output::Sqlite(path) => Self::Sqlite {
+                store: sqlite::Store::open(
+                    path,
+                    T::KIND,
+                    options.query,
+                    &options.search_mode.to_string(),
+                )?,
+            },
+            Output::Postgres(_) if out.is_some() => {
+                anyhow::bail!(
+                    "--out cannot be used with postgres output, pass the database URL instead"
+                )
+            }
+            #[cfg(feature = "postgres")]
+            Output::Postgres(url) => Self::Postgres {
+                store: postgres::Store::open(
+                    url,
+                    T::KIND,
+                    options.query,
+                    &options.search_mode.to_string(),
+                )
+                .await?,
+            },
+            #[cfg(not(feature = "postgres"))]
+            Output::Postgres(_) => {
+                anyhow::bail!("postgres output needs a build with the `postgres` feature")
+            }
+        })
+    }
+
+    pub(crate) async fn write(&mut self, page: Vec<T>, next: Option<&str>) -> anyhow::Result<()> {
+        match self {
+            Self::PrettyPrint { index } => {
+                for item in &page {
+                    *index += 1;
+                    println!("\n{} #{index}:", T::NAME);
+                    item.pretty_print();
+                }
+            }
+            Self::Json {
+                items,
+                next: cursor,
+                duplicates,
+                ..
+            } => {
+                *duplicates += types::merge(items, page);
+                *cursor = next.map(ToOwned::to_owned);
+            }
+            Self::JsonLines { writer, .. } => {
+                for item in &page {
+                    serde_json::to_writer(&mut *writer, item)?;
+                    writer.write_all(b"\n")?;
+                }
+                writer.flush()?;
+            }
+            Self::Csv {
+                writer, columns, ..
+            } => {
+                for item in &page {
+                    writer.write_record(columns.iter().map(|column| item.column(column)))?;
+                }
+            }
+            #[cfg(feature = "parquet")]
+            Self::Parquet { writer } => {
+                writer.write(&T::batch(&page)?)?;
+            }
+            Self::Html {
+                items, duplicates, ..
+            } => *duplicates += types::merge(items, page),
+            Self::Sqlite { store } => T::store(store, &page)?,
+            #[cfg(feature = "postgres")]
+            Self::Postgres { store } => T::upsert(store, &page).await?,
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn flush(&mut self) -> anyhow::Result<()> {
+        match self {
+            Self::PrettyPrint { .. } => std::io::stdout().flush()?,
+            Self::Json { .. } => {}
+            Self::JsonLines { writer, .. } => writer.flush()?,
+            Self::Csv { writer, .. } => writer.flush()?,
+            #[cfg(feature = "parquet")]
+            Self::Parquet { .. } => {}
+            Self::Html { .. } | Self::Sqlite { .. } => {}
+            #[cfg(feature = "postgres")]
+            Self::Postgres { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn commit(&mut self) -> anyhow::Result<Option<u64>> {
+        self.flush().await?;
+
+        let path = match &*self {
+            Self::JsonLines {
+                path: Some(path), ..
+            }
+            | Self::Csv {
+                path: Some(path), ..
+            } => path,
+            _ => return Ok(None),
+        };
+
+        let metadata =
+            std::fs::metadata(path).with_context(|| format!("reading `{}`", path.display()))?;
+
+        Ok(Some(metadata.len()))
+    }
+
+    pub(crate) async fn finish(mut self) -> anyhow::Result<()> {
+        self.flush().await?;
+
+        match self {
+            Self::Json {
+                path,
+                items,
+                next,
+                duplicates,
+            } => {
+                write_document(path.as_deref(), &items, next.as_deref())?;
+
+                if duplicates > 0 {
+                    eprintln!("Collapsed {duplicates} duplicate {}", T::KIND);
+                }
+            }
+            #[cfg(feature = "parquet")]
+            Self::Parquet { writer } => {
+                writer.close()?;
+            }
+            Self::Html {
+                dir,
+                items,
+                profile,
+                duplicates,
+            } => {
+                T::archive(&dir, &items, profile.as_ref())?;
+
+                if duplicates > 0 {
+                    eprintln!("Collapsed {duplicates} duplicate {}", T::KIND);
+                }
+                eprintln!(
+                    "Wrote archive of {} {} to `{}`",
+                    items.len(),
+                    T::KIND,
+                    dir.display()
+                );
+            }
+            Self::Sqlite { store } => store.finish()?,
+            #[cfg(feature = "postgres")]
+            Self::Postgres { store } => store.finish().await?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn html_dir(out: Option<&Path>) -> anyhow::Result<PathBuf> {
+    match out {
+        Some(path) if path == Path::new("-") => {
+            anyhow::bail!("html output needs a directory, not stdout")
+        }
+        Some(path) => Ok(path.to_owned()),
+        None => Ok(PathBuf::from("archive")),
+    }
+}
+
+fn select_columns<T: Record>(columns: &[String]) -> anyhow::Result<Vec<&'static str>> {
+    if columns.is_empty() {
+        return Ok(T::COLUMNS.to_vec());
+    }
+
+    columns
+        .iter()
+        .map(|column| {
+            T::COLUMNS
+                .iter()
+                .find(|known| **known == column)
+                .copied()
+                .ok_or_else(|| {
+                    anyhow::format_err!(
+                        "unknown column `{column}`, expected one of: {}",
+                        T::COLUMNS.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+fn writer(out: Option<&Path>, append: bool) -> anyhow::Result<Box<dyn Write + Send>> {
+    Ok(match out {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(append)
+                .truncate(!append)
+                .open(path)
+                .with_context(|| format!("opening `{}`", path.display()))?;
+            Box::new(std::io::BufWriter::new(file))
+        }
+        None => Box::new(std::io::BufWriter::new(std::io::stdout())),
+    })
+}
+
+fn rewind(path: Option<&Path>, committed: Option<u64>) -> anyhow::Result<()> {
+    let (Some(path), Some(committed)) = (path, committed) else {
+        return Ok(());
+    };
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("opening `{}`", path.display()))?;
+    if file.metadata()?.len() > committed {
+        file.set_len(committed)
+            .with_context(|| format!("truncating `{}`", path.display()))?;
+    }
+
+    Ok(())
+}
+
+struct Document<'a, T> {
+    items: &'a [T],
+    next: Option<&'a str>,
+}
+
+impl<T: Record> Serialize for Document<'_, T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry(T::KIND, self.items)?;
+        map.serialize_entry("next", &self.next)?;
+        map.serialize_entry("previous", &None::<String>)?;
+        map.end()
+    }
+}
+
+fn write_document<T: Record>(
+    path: Option<&Path>,
+    items: &[T],
+    next: Option<&str>,
+) -> anyhow::Result<()> {
+    let document = Document { items, next };
+
+    match path {
+        Some(path) => {
+            let json = serde_json::to_string_pretty(&document)?;
+            std::fs::write(path, json).with_context(|| format!("writing `{}`", path.display()))
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            serde_json::to_writer_pretty(&mut stdout, &document)?;
+            writeln!(stdout)?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tweet(id: &str, text: &str) -> Tweet {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "text": text,
+            "hashtags": [],
+            "urls": [],
+            "mentions": [],
+            "photos": [],
+            "videos": [],
+            "thread": [],
+        }))
+        .unwrap()
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "twitter-scraper-output-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn options<'a>(output: &'a Output, out: &'a Path, columns: &'a [String]) -> SinkOptions<'a> {
+        SinkOptions {
+            output,
+            out: Some(out),
+            checkpoint: false,
+            resume: false,
+            committed: None,
+            columns,
+            query: "rust",
+            search_mode: SearchMode::Latest,
+            profile: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn resume_drops_rows_written_after_the_checkpoint() {
+        let path = scratch("resume.jsonl");
+        let output = Output::Jsonl;
+
+        let checkpointed = SinkOptions {
+            checkpoint: true,
+            ..options(&output, &path, &[])
+        };
+        let mut sink = Sink::open(&checkpointed).await.unwrap();
+        sink.write(vec![tweet("1", "one")], None).await.unwrap();
+        let committed = sink.commit().await.unwrap();
+        sink.write(vec![tweet("2", "two")], None).await.unwrap();
+        sink.finish().await.unwrap();
+
+        let resumed = SinkOptions {
+            resume: true,
+            committed,
+            ..checkpointed
+        };
+        let mut sink = Sink::open(&resumed).await.unwrap();
+        sink.write(vec![tweet("2", "again")], None).await.unwrap();
+        sink.finish().await.unwrap();
+
+        let texts = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Tweet>(line).unwrap().text.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["one", "again"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn checkpoints_refuse_outputs_written_at_the_end() {
+        let path = scratch("buffered");
+        let mut outputs = vec![Output::Json, Output::Html];
+        if cfg!(feature = "parquet") {
+            outputs.push(Output::Parquet);
+        }
+
+        for output in &outputs {
+            for resume in [false, true] {
+                let options = SinkOptions {
+                    checkpoint: true,
+                    resume,
+                    ..options(output, &path, &[])
+                };
+                let Err(error) = Sink::<Tweet>::open(&options).await else {
+                    panic!("{output} output was opened for a checkpointed run");
+                };
+                assert!(error.to_string().contains("--checkpoint"), "{error}");
+            }
+        }
+
+        let stdout = SinkOptions {
+            checkpoint: true,
+            ..options(&Output::Json, Path::new("-"), &[])
+        };
+        assert!(Sink::<Tweet>::open(&stdout).await.is_err());
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn json_document_is_written_on_finish() {
+        let path = scratch("document.json");
+        let output = Output::Json;
+
+        let mut sink = Sink::open(&options(&output, &path, &[])).await.unwrap();
+        sink.write(vec![tweet("1", "one")], Some("a"))
+            .await
+            .unwrap();
+        sink.write(vec![tweet("2", "two")], Some("b"))
+            .await
+            .unwrap();
+        assert_eq!(sink.commit().await.unwrap(), None);
+        assert!(!path.exists());
+        sink.finish().await.unwrap();
+
+        let document =
+            serde_json::from_str::<serde_json::Value>(&std::fs::read_to_string(&path).unwrap())
+                .unwrap();
+        assert_eq!(document["tweets"].as_array().unwrap().len(), 2);
+        assert_eq!(document["next"], "b");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn json_lines_reach_the_file_after_every_page() {
+        let path = scratch("stream.jsonl");
+        let output = Output::Jsonl;
+
+        let mut sink = Sink::open(&options(&output, &path, &[])).await.unwrap();
+        sink.write(vec![tweet("1", "one")], Some("a"))
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+        sink.write(vec![tweet("2", "two")], None).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+        sink.finish().await.unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn csv_quotes_fields_and_keeps_selected_columns() {
+        let path = scratch("columns.csv");
+        let output = Output::Csv;
+        let columns = ["text", "id", "hashtags"].map(ToOwned::to_owned);
+
+        let mut quoted = tweet("1", "say \"hi\", then\nleave");
+        quoted.hashtags = vec!["rust".to_owned(), "csv".to_owned()];
+
+        let mut sink = Sink::open(&options(&output, &path, &columns))
+            .await
+            .unwrap();
+        sink.write(vec![quoted, tweet("2", "plain")], None)
+            .await
+            .unwrap();
+        sink.finish().await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "text,id,hashtags\n\"say \"\"hi\"\", then\nleave\",1,rust csv\nplain,2,\n"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_and_misplaced_columns() {
+        let path = scratch("unknown.csv");
+        let unknown = ["id", "favourites"].map(ToOwned::to_owned);
+
+        let error = Sink::<Tweet>::open(&options(&Output::Csv, &path, &unknown))
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("unknown column `favourites`"));
+
+        let error = Sink::<Tweet>::open(&options(&Output::Jsonl, &path, &unknown[..1]))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "--columns can only be used with csv output"
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use std::sync::Arc;
+
+use arrow_array::{
+    builder::{ListBuilder, StringBuilder, StructBuilder},
+    ArrayRef, BooleanArray, Int32Array, RecordBatch, StringArray, TimestampMillisecondArray,
+};
+use arrow_schema::{DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, Utc};
+
+use crate::types::{profile::Profile, tweets::Tweet};
+
+const MENTION: &[&str] = &["id", "username", "name"];
+const PHOTO: &[&str] = &["id", "url", "alt_text"];
+const VIDEO: &[&str] = &["id", "preview", "url"];
+
+pub(super) fn tweet_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, true),
+        Field::new("created_at", timestamp(), true),
+        Field::new("user_id", DataType::Utf8, true),
+        Field::new("username", DataType::Utf8, true),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("text", DataType::Utf8, true),
+        Field::new("likes", DataType::Int32, true),
+        Field::new("retweets", DataType::Int32, true),
+        Field::new("replies", DataType::Int32, true),
+        Field::new("quotes", DataType::Int32, true),
+        Field::new("views", DataType::Int32, true),
+        Field::new("bookmarks", DataType::Int32, true),
+        Field::new("hashtags", list(DataType::Utf8), true),
+        Field::new("urls", list(DataType::Utf8), true),
+        Field::new("mentions", list(strings(MENTION)), true),
+        Field::new("photos", list(strings(PHOTO)), true),
+        Field::new("videos", list(strings(VIDEO)), true),
+        Field::new("conversation_id", DataType::Utf8, true),
+        Field::new("in_reply_to_status_id", DataType::Utf8, true),
+        Field::new("quoted_status_id", DataType::Utf8, true),
+        Field::new("retweeted_status_id", DataType::Utf8, true),
+        Field::new("is_reply", DataType::Boolean, true),
+        Field::new("is_retweet", DataType::Boolean, true),
+        Field::new("is_quoted", DataType::Boolean, true),
+        Field::new("sensitive_content", DataType::Boolean, true),
+        Field::new("permanent_url", DataType::Utf8, true),
+    ]))
+}
+
+pub(super) fn tweet_batch(tweets: &[Tweet]) -> anyhow::Result<RecordBatch> {
+    let columns: Vec<ArrayRef> = vec![
+        text(tweets, |tweet| tweet.id.as_deref()),
+        time(tweets, |tweet| tweet.time_parsed),
+        text(tweets, |tweet| tweet.user_id.as_deref()),
+        text(tweets, |tweet| tweet.username.as_deref()),
+        text(tweets, |tweet| tweet.name.as_deref()),
+        text(tweets, |tweet| tweet.text.as_deref()),
+        number(tweets, |tweet| tweet.likes),
+        number(tweets, |tweet| tweet.retweets.or(tweet.retweet_count)),
+        number(tweets, |tweet| tweet.replies.or(tweet.reply_count)),
+        number(tweets, |tweet| tweet.quote_count),
+        number(tweets, |tweet| tweet.views.or(tweet.ext_views)),
+        number(tweets, |tweet| tweet.bookmark_count),
+        text_list(tweets, |tweet| &tweet.hashtags),
+        text_list(tweets, |tweet| &tweet.urls),
+        struct_list(tweets, MENTION, |tweet| {
+            tweet
+                .mentions
+                .iter()
+                .map(|mention| {
+                    vec![
+                        Some(mention.id.as_str()),
+                        mention.username.as_deref(),
+                        mention.name.as_deref(),
+                    ]
+                })
+                .collect()
+        }),
+        struct_list(tweets, PHOTO, |tweet| {
+            tweet
+                .photos
+                .iter()
+                .map(|photo| {
+                    vec![
+                        Some(photo.id.as_str()),
+                        Some(photo.url.as_str()),
+                        photo.alt_text.as_deref(),
+                    ]
+                })
+                .collect()
+        }),
+        struct_list(tweets, VIDEO, |tweet| {
+            tweet
+                .videos
+                .iter()
+                .map(|video| {
+                    vec![
+                        Some(video.id.as_str()),
+                        Some(video.preview.as_str()),
+                        video.url.as_deref(),
+                    ]
+                })
+                .collect()
+        }),
+        text(tweets, |tweet| tweet.conversation_id.as_deref()),
+        text(tweets, |tweet| tweet.in_reply_to_status_id.as_deref()),
+        text(tweets, |tweet| tweet.quoted_status_id.as_deref()),
+        text(tweets, |tweet| tweet.retweeted_status_id.as_deref()),
+        flag(tweets, |tweet| tweet.is_reply),
+        flag(tweets, |tweet| tweet.is_retweet),
+        flag(tweets, |tweet| tweet.is_quoted),
+        flag(tweets, |tweet| tweet.sensitive_content),
+        text(tweets, |tweet| tweet.permanent_url.as_deref()),
+    ];
+
+    Ok(RecordBatch::try_new(tweet_schema(), columns)?)
+}
+
+pub(super) fn profile_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("username", DataType::Utf8, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("description", DataType::Utf8, true),
+        Field::new("location", DataType::Utf8, true),
+        Field::new("url", DataType::Utf8, true),
+        Field::new("created_at", timestamp(), true),
+        Field::new("followers", DataType::Int32, true),
+        Field::new("following", DataType::Int32, true),
+        Field::new("tweets", DataType::Int32, true),
+        Field::new("listed", DataType::Int32, true),
+        Field::new("protected", DataType::Boolean, true),
+        Field::new("verified", DataType::Boolean, true),
+        Field::new("is_blue_verified", DataType::Boolean, true),
+        Field::new("profile_image_url", DataType::Utf8, true),
+        Field::new("profile_banner_url", DataType::Utf8, true),
+        Field::new("pinned_tweet_id", DataType::Utf8, true),
+    ]))
+}
+
+pub(super) fn profile_batch(profiles: &[Profile]) -> anyhow::Result<RecordBatch> {
+    let columns: Vec<ArrayRef> = vec![
+        text(profiles, |profile| Some(profile.id.as_str())),
+        text(profiles, |profile| Some(profile.username.as_str())),
+        text(profiles, |profile| Some(profile.name.as_str())),
+        text(profiles, |profile| profile.description.as_deref()),
+        text(profiles, |profile| profile.location.as_deref()),
+        text(profiles, |profile| profile.url.as_deref()),
+        time(profiles, |profile| Some(profile.created_at)),
+        number(profiles, |profile| Some(profile.followers_count)),
+        number(profiles, |profile| Some(profile.following_count)),
+        number(profiles, |profile| Some(profile.tweets_count)),
+        number(profiles, |profile| Some(profile.listed_count)),
+        flag(profiles, |profile| Some(profile.protected)),
+        flag(profiles, |profile| Some(profile.verified)),
+        flag(profiles, |profile| profile.is_blue_verified),
+        text(profiles, |profile| profile.profile_image_url.as_deref()),
+        text(profiles, |profile| profile.profile_banner_url.as_deref()),
+        text(profiles, |profile| profile.pinned_tweet_id.as_deref()),
+    ];
+
+    Ok(RecordBatch::try_new(profile_schema(), columns)?)
+}
+
+fn timestamp() -> DataType {
+    DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+}
+
+fn list(data_type: DataType) -> DataType {
+    DataType::List(Arc::new(Field::new("item", data_type, true)))
+}
+
+fn fields(names: &[&str]) -> Fields {
+    names
+        .iter()
+        .map(|name| Field::new(*name, DataType::Utf8, true))
+        .collect()
+}
+
+fn strings(names: &[&str]) -> DataType {
+    DataType::Struct(fields(names))
+}
+
+fn text<T>(items: &[T], value: impl Fn(&T) -> Option<&str>) -> ArrayRef {
+    Arc::new(items.iter().map(value).collect::<StringArray>())
+}
+
+fn number<T>(items: &[T], value: impl Fn(&T) -> Option<i32>) -> ArrayRef {
+    Arc::new(items.iter().map(value).collect::<Int32Array>())
+}
+
+fn flag<T>(items: &[T], value: impl Fn(&T) -> Option<bool>) -> ArrayRef {
+    Arc::new(items.iter().map(value).collect::<BooleanArray>())
+}
+
+fn time<T>(items: &[T], value: impl Fn(&T) -> Option<DateTime<Utc>>) -> ArrayRef {
+    let array = items
+        .iter()
+        .map(|item| value(item).map(|time| time.timestamp_millis()))
+        .collect::<TimestampMillisecondArray>();
+
+    Arc::new(array.with_timezone("UTC"))
+}
+
+fn text_list<T>(items: &[T], values: impl Fn(&T) -> &[String]) -> ArrayRef {
+    let mut builder = ListBuilder::new(StringBuilder::new());
+
+    for item in items {
+        for value in values(item) {
+            builder.values().append_value(value);
+        }
+        builder.append(true);
+    }
+
+    Arc::new(builder.finish())
+}
+
+fn struct_list<T>(
+    items: &[T],
+    names: &[&str],
+    entries: impl Fn(&T) -> Vec<Vec<Option<&str>>>,
+) -> ArrayRef {
+    let mut builder = ListBuilder::new(StructBuilder::from_fields(fields(names), 0));
+
+    for item in items {
+        let values = builder.values();
+        for entry in entries(item) {
+            for (index, value) in entry.into_iter().enumerate() {
+                values
+                    .field_builder::<StringBuilder>(index)
+                    .expect("struct fields are strings")
+                    .append_option(value);
+            }
+            values.append(true);
+        }
+        builder.append(true);
+    }
+
+    Arc::new(builder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::Array as _;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::{
+        options::Output,
+        output::{Sink, SinkOptions},
+        scraper::SearchMode,
+    };
+
+    fn tweet(id: &str, hashtags: &[&str]) -> Tweet {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "text": format!("tweet {id}"),
+            "likes": 3,
+            "time_parsed": "2024-06-01T12:34:56Z",
+            "hashtags": hashtags,
+            "urls": [],
+            "mentions": [{ "id": "7", "username": "rustlang", "name": "Rust" }],
+            "photos": [],
+            "videos": [],
+            "thread": [],
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn writes_pages_into_one_row_group_and_reads_back() {
+        let path = std::env::temp_dir().join(format!(
+            "twitter-scraper-columnar-{}.parquet",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let output = Output::Parquet;
+        let options = SinkOptions {
+            output: &output,
+            out: Some(&path),
+            checkpoint: false,
+            resume: false,
+            committed: None,
+            columns: &[],
+            query: "rust",
+            search_mode: SearchMode::Latest,
+            profile: None,
+        };
+
+        let mut sink = Sink::open(&options).await.unwrap();
+        for page in [["1", "2"], ["3", "4"], ["5", "6"]] {
+            let tweets = page.iter().map(|id| tweet(id, &["rust"])).collect();
+            sink.write(tweets, None).await.unwrap();
+            sink.commit().await.unwrap();
+        }
+        sink.finish().await.unwrap();
+
+        let reader =
+            ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 1);
+        assert_eq!(reader.schema(), &tweet_schema());
+
+        let batches = reader
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+
+        let ids = batch
+            .column_by_name("id")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(
+            ids.iter().flatten().collect::<Vec<_>>(),
+            ["1", "2", "3", "4", "5", "6"]
+        );
+
+        let likes = batch
+            .column_by_name("likes")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+        assert!(likes.iter().all(|value| value == Some(3)));
+
+        let created = batch
+            .column_by_name("created_at")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+            .unwrap();
+        assert_eq!(created.value(0), 1_717_245_296_000);
+        assert_eq!(batch.column_by_name("mentions").unwrap().null_count(), 0);
+    }
+}
@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+
+use crate::types::{profile::Profile, tweets::Tweet};
+
+pub(super) const TWEET: &[&str] = &[
+    "id",
+    "created_at",
+    "user_id",
+    "username",
+    "name",
+    "text",
+    "likes",
+    "retweets",
+    "replies",
+    "quotes",
+    "views",
+    "bookmarks",
+    "hashtags",
+    "urls",
+    "mentions",
+    "photos",
+    "videos",
+    "conversation_id",
+    "in_reply_to_status_id",
+    "quoted_status_id",
+    "retweeted_status_id",
+    "is_reply",
+    "is_retweet",
+    "is_quoted",
+    "sensitive_content",
+    "permanent_url",
+];
+
+pub(super) const PROFILE: &[&str] = &[
+    "id",
+    "username",
+    "name",
+    "description",
+    "location",
+    "url",
+    "created_at",
+    "followers",
+    "following",
+    "tweets",
+    "listed",
+    "protected",
+    "verified",
+    "is_blue_verified",
+    "profile_image_url",
+    "profile_banner_url",
+    "pinned_tweet_id",
+];
+
+pub(super) fn tweet(tweet: &Tweet, column: &str) -> String {
+    match column {
+        "id" => text(&tweet.id),
+        "created_at" => tweet
+            .time_parsed
+            .map(time)
+            .unwrap_or_else(|| text(&tweet.created_at)),
+        "user_id" => text(&tweet.user_id),
+        "username" => text(&tweet.username),
+        "name" => text(&tweet.name),
+        "text" => text(&tweet.text),
+        "likes" => number(tweet.likes),
+        "retweets" => number(tweet.retweets.or(tweet.retweet_count)),
+        "replies" => number(tweet.replies.or(tweet.reply_count)),
+        "quotes" => number(tweet.quote_count),
+        "views" => number(tweet.views.or(tweet.ext_views)),
+        "bookmarks" => number(tweet.bookmark_count),
+        "hashtags" => tweet.hashtags.join(" "),
+        "urls" => tweet.urls.join(" "),
+        "mentions" => tweet
+            .mentions
+            .iter()
+            .filter_map(|mention| mention.username.as_deref())
+            .collect::<Vec<_>>()
+            .join(" "),
+        "photos" => tweet.photos.len().to_string(),
+        "videos" => tweet.videos.len().to_string(),
+        "conversation_id" => text(&tweet.conversation_id),
+        "in_reply_to_status_id" => text(&tweet.in_reply_to_status_id),
+        "quoted_status_id" => text(&tweet.quoted_status_id),
+        "retweeted_status_id" => text(&tweet.retweeted_status_id),
+        "is_reply" => flag(tweet.is_reply),
+        "is_retweet" => flag(tweet.is_retweet),
+        "is_quoted" => flag(tweet.is_quoted),
+        "sensitive_content" => flag(tweet.sensitive_content),
+        "permanent_url" => text(&tweet.permanent_url),
+        _ => unreachable!("`{column}` is not a tweet column"),
+    }
+}
+
+pub(super) fn profile(profile: &Profile, column: &str) -> String {
+    match column {
+        "id" => profile.id.clone(),
+        "username" => profile.username.clone(),
+        "name" => profile.name.clone(),
+        "description" => text(&profile.description),
+        "location" => text(&profile.location),
+        "url" => text(&profile.url),
+        "created_at" => time(profile.created_at),
+        "followers" => profile.followers_count.to_string(),
+        "following" => profile.following_count.to_string(),
+        "tweets" => profile.tweets_count.to_string(),
+        "listed" => profile.listed_count.to_string(),
+        "protected" => profile.protected.to_string(),
+        "verified" => profile.verified.to_string(),
+        "is_blue_verified" => flag(profile.is_blue_verified),
+        "profile_image_url" => text(&profile.profile_image_url),
+        "profile_banner_url" => text(&profile.profile_banner_url),
+        "pinned_tweet_id" => text(&profile.pinned_tweet_id),
+        _ => unreachable!("`{column}` is not a profile column"),
+    }
+}
+
+fn text(value: &Option<String>) -> String {
+    value.clone().unwrap_or_default()
+}
+
+fn number(value: Option<i32>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn flag(value: Option<bool>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn time(value: DateTime<Utc>) -> String {
+    value.to_rfc3339()
+}
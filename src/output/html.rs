@@ -0,0 +1,430 @@
+use std::{collections::HashSet, path::Path, sync::LazyLock};
+
+use anyhow::Context as _;
+use minijinja::{context, Environment};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    media::Library,
+    types::{profile::Profile, tweets::Tweet},
+};
+
+static SHORT_URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"https?://t\.co/[A-Za-z0-9]+").unwrap());
+
+const TEMPLATES: &[(&str, &str)] = &[
+    ("base.html", include_str!("html/base.html")),
+    ("macros.html", include_str!("html/macros.html")),
+    ("index.html", include_str!("html/index.html")),
+    ("tweet.html", include_str!("html/tweet.html")),
+];
+
+#[derive(Serialize)]
+struct Segment {
+    text: String,
+    href: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Image {
+    src: Option<String>,
+    url: String,
+    alt: Option<String>,
+    embed: bool,
+}
+
+#[derive(Serialize)]
+struct Reply {
+    id: String,
+    page: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TweetView {
+    id: Option<String>,
+    page: Option<String>,
+    permanent_url: Option<String>,
+    username: Option<String>,
+    name: Option<String>,
+    time: Option<String>,
+    segments: Vec<Segment>,
+    photos: Vec<Image>,
+    videos: Vec<Image>,
+    likes: Option<i32>,
+    retweets: Option<i32>,
+    replies: Option<i32>,
+    views: Option<i32>,
+    reply_to: Option<Reply>,
+    quoted: Option<Box<Self>>,
+    retweeted: Option<Box<Self>>,
+    thread: Vec<Self>,
+}
+
+#[derive(Serialize)]
+struct ProfileView<'a> {
+    profile: &'a Profile,
+    joined: String,
+    avatar: Option<String>,
+    banner: Option<String>,
+}
+
+struct Archive<'a> {
+    library: Library,
+    ids: HashSet<&'a str>,
+    root: &'static str,
+}
+
+impl Archive<'_> {
+    fn page(&self, id: &str) -> Option<String> {
+        self.ids
+            .contains(id)
+            .then(|| format!("{}tweets/{id}.html", self.root))
+    }
+
+    fn local(&self, file: Option<&str>) -> Option<String> {
+        file.map(|file| format!("{}media/{file}", self.root))
+    }
+
+    fn tweet(&self, tweet: &Tweet) -> TweetView {
+        TweetView {
+            id: tweet.id.clone(),
+            page: tweet.id.as_deref().and_then(|id| self.page(id)),
+            permanent_url: tweet.permanent_url.clone(),
+            username: tweet.username.clone(),
+            name: tweet.name.clone(),
+            time: tweet
+                .time_parsed
+                .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string()),
+            segments: segments(tweet),
+            photos: tweet
+                .photos
+                .iter()
+                .map(|photo| Image {
+                    src: self.local(self.library.photo(photo)),
+                    url: photo.url.clone(),
+                    alt: photo.alt_text.clone(),
+                    embed: true,
+                })
+                .collect(),
+            videos: tweet
+                .videos
+                .iter()
+                .map(|video| {
+                    let file = self.library.video(video);
+                    Image {
+                        src: self.local(file),
+                        url: video
+                            .url
+                            .clone()
+                            .or_else(|| video.playlist.clone())
+                            .unwrap_or_else(|| video.preview.clone()),
+                        alt: None,
+                        embed: file.is_some_and(|file| !file.ends_with(".ts")),
+                    }
+                })
+                .collect(),
+            likes: tweet.likes,
+            retweets: tweet.retweets.or(tweet.retweet_count),
+            replies: tweet.replies.or(tweet.reply_count),
+            views: tweet.views.or(tweet.ext_views),
+            reply_to: tweet.in_reply_to_status_id.as_ref().map(|id| Reply {
+                id: id.clone(),
+                page: self.page(id),
+            }),
+            quoted: tweet
+                .quoted_status
+                .as_deref()
+                .map(|quoted| Box::new(self.tweet(quoted))),
+            retweeted: tweet
+                .retweeted_status
+                .as_deref()
+                .map(|retweeted| Box::new(self.tweet(retweeted))),
+            thread: tweet
+                .thread
+                .iter()
+                .filter(|reply| reply.id != tweet.id)
+                .map(|reply| self.tweet(reply))
+                .collect(),
+        }
+    }
+
+    fn profile<'p>(&self, profile: &'p Profile) -> ProfileView<'p> {
+        let image = |url: &Option<String>| {
+            let file = url.as_deref().and_then(|url| self.library.image(url));
+            self.local(file)
+        };
+
+        ProfileView {
+            profile,
+            joined: profile.created_at.format("%B %Y").to_string(),
+            avatar: image(&profile.profile_image_url),
+            banner: image(&profile.profile_banner_url),
+        }
+    }
+}
+
+pub(super) fn render(
+    dir: &Path,
+    tweets: &[Tweet],
+    profile: Option<&Profile>,
+) -> anyhow::Result<()> {
+    let mut environment = Environment::new();
+    environment.set_trim_blocks(true);
+    environment.set_lstrip_blocks(true);
+    for (name, source) in TEMPLATES {
+        environment.add_template(name, source)?;
+    }
+
+    let pages = dir.join("tweets");
+    std::fs::create_dir_all(&pages).with_context(|| format!("creating `{}`", pages.display()))?;
+
+    let ids = tweets
+        .iter()
+        .filter_map(|tweet| tweet.id.as_deref())
+        .collect();
+    let mut archive = Archive {
+        library: Library::open(&dir.join("media"))?,
+        ids,
+        root: "",
+    };
+
+    let index = environment.get_template("index.html")?.render(context! {
+        root => archive.root,
+        profile => profile.map(|profile| archive.profile(profile)),
+        tweets => tweets.iter().map(|tweet| archive.tweet(tweet)).collect::<Vec<_>>(),
+    })?;
+    write(&dir.join("index.html"), &index)?;
+
+    archive.root = "../";
+    let template = environment.get_template("tweet.html")?;
+    for tweet in tweets {
+        let Some(id) = &tweet.id else {
+            continue;
+        };
+
+        let page = template.render(context! {
+            root => archive.root,
+            profile => profile.map(|profile| archive.profile(profile)),
+            tweet => archive.tweet(tweet),
+        })?;
+        write(&pages.join(format!("{id}.html")), &page)?;
+    }
+
+    Ok(())
+}
+
+fn segments(tweet: &Tweet) -> Vec<Segment> {
+    let text = tweet.text.as_deref().unwrap_or_default();
+    let mut urls = tweet.urls.iter();
+    let mut segments = Vec::new();
+    let mut last = 0;
+
+    for short in SHORT_URL.find_iter(text) {
+        segments.push(Segment {
+            text: text[last..short.start()].to_owned(),
+            href: None,
+        });
+        last = short.end();
+
+        if let Some(url) = urls.next() {
+            let display = url
+                .trim_start_matches("https://")
+                .trim_start_matches("http://")
+                .trim_start_matches("www.");
+            segments.push(Segment {
+                text: display.to_owned(),
+                href: Some(url.clone()),
+            });
+        }
+    }
+
+    segments.push(Segment {
+        text: text[last..].to_owned(),
+        href: None,
+    });
+    segments.retain(|segment| !segment.text.is_empty());
+
+    if let Some(segment) = segments.last_mut().filter(|segment| segment.href.is_none()) {
+        segment.text.truncate(segment.text.trim_end().len());
+    }
+
+    segments
+}
+
+fn write(path: &Path, html: &str) -> anyhow::Result<()> {
+    std::fs::write(path, html).with_context(|| format!("writing `{}`", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_short_urls_and_drops_media_links() {
+        let tweet: Tweet = serde_json::from_value(serde_json::json!({
+            "text": "Read https://t.co/abc now https://t.co/media",
+            "hashtags": [],
+            "urls": ["https://blog.rust-lang.org/2015/05/15/Rust-1.0.html"],
+            "mentions": [],
+            "photos": [],
+            "videos": [],
+            "thread": [],
+        }))
+        .unwrap();
+
+        let segments = segments(&tweet)
+            .into_iter()
+            .map(|segment| (segment.text, segment.href))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            segments,
+            [
+                ("Read ".to_owned(), None),
+                (
+                    "blog.rust-lang.org/2015/05/15/Rust-1.0.html".to_owned(),
+                    Some("https://blog.rust-lang.org/2015/05/15/Rust-1.0.html".to_owned())
+                ),
+                (" now".to_owned(), None),
+            ]
+        );
+    }
+
+    fn tweet(value: serde_json::Value) -> Tweet {
+        let mut tweet = serde_json::json!({
+            "hashtags": [],
+            "urls": [],
+            "mentions": [],
+            "photos": [],
+            "videos": [],
+            "thread": [],
+        });
+        tweet
+            .as_object_mut()
+            .unwrap()
+            .extend(value.as_object().unwrap().clone());
+
+        serde_json::from_value(tweet).unwrap()
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .replace("&#x2f;", "/")
+    }
+
+    fn position(html: &str, needle: &str) -> usize {
+        html.find(needle)
+            .unwrap_or_else(|| panic!("`{needle}` is missing from:\n{html}"))
+    }
+
+    #[test]
+    fn renders_pages_with_threads_quotes_and_local_media() {
+        let dir = std::env::temp_dir().join(format!("twitter-scraper-html-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let file = format!("{}.jpg", "a".repeat(64));
+        std::fs::create_dir_all(dir.join("media")).unwrap();
+        std::fs::write(dir.join("media").join(&file), b"image").unwrap();
+        std::fs::write(
+            dir.join("media").join(format!("{file}.json")),
+            serde_json::json!({
+                "file": file,
+                "sha256": "a".repeat(64),
+                "size": 5,
+                "kind": "photo",
+                "sources": [
+                    {
+                        "tweet_id": "1",
+                        "media_id": "p1",
+                        "url": "https://pbs.twimg.com/media/local.jpg?name=orig",
+                    },
+                    {
+                        "tweet_id": "9",
+                        "media_id": "p9",
+                        "url": "https://pbs.twimg.com/media/remote.jpg?name=orig",
+                    },
+                ],
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let stream = format!("{}.ts", "b".repeat(64));
+        std::fs::write(dir.join("media").join(&stream), b"stream").unwrap();
+        std::fs::write(
+            dir.join("media").join(format!("{stream}.json")),
+            serde_json::json!({
+                "file": stream,
+                "sha256": "b".repeat(64),
+                "size": 6,
+                "kind": "video",
+                "sources": [{
+                    "tweet_id": "2",
+                    "media_id": "v2",
+                    "url": "https://video.twimg.com/pl/master.m3u8",
+                }],
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let reply = tweet(serde_json::json!({
+            "id": "2",
+            "username": "rustlang",
+            "text": "and a reply",
+            "time_parsed": "2024-06-01T12:40:00Z",
+            "videos": [{
+                "id": "v2",
+                "preview": "",
+                "playlist": "https://video.twimg.com/pl/master.m3u8",
+            }],
+            "in_reply_to_status_id": "1",
+        }));
+        let quoted = tweet(serde_json::json!({
+            "id": "9",
+            "username": "ferris",
+            "text": "the quoted post",
+            "photos": [{ "id": "p9", "url": "https://pbs.twimg.com/media/remote.jpg" }],
+        }));
+        let mut first = tweet(serde_json::json!({
+            "id": "1",
+            "username": "rustlang",
+            "text": "the first post",
+            "time_parsed": "2024-06-01T12:34:56Z",
+            "photos": [{ "id": "p1", "url": "https://pbs.twimg.com/media/local.jpg?name=small" }],
+        }));
+        first.quoted_status = Some(Box::new(quoted));
+        first.thread = vec![first.clone(), reply.clone()];
+
+        render(&dir, &[first, reply], None).unwrap();
+
+        let index = read(&dir.join("index.html"));
+        position(&index, &format!(r#"src="media/{file}""#));
+        position(&index, r#"href="tweets/2.html""#);
+
+        let page = read(&dir.join("tweets/1.html"));
+        position(&page, r#"href="../index.html""#);
+        position(&page, &format!(r#"src="../media/{file}""#));
+        position(&page, r#"href="../tweets/2.html""#);
+        assert!(!page.contains(r#"src="media/"#));
+
+        let first = position(&page, r#"id="tweet-1""#);
+        let quoted = position(&page, r#"id="tweet-9""#);
+        let photo = quoted + position(&page[quoted..], &format!(r#"<img src="../media/{file}""#));
+        let thread = position(&page, r#"<div class="thread">"#);
+        let reply = position(&page, r#"id="tweet-2""#);
+        assert!(first < quoted && quoted < photo && photo < thread && thread < reply);
+        assert_eq!(page.matches(r#"id="tweet-1""#).count(), 1);
+        assert!(!page.contains("remote.jpg"));
+        position(&page, &format!(r#"<a href="../media/{stream}">"#));
+        assert!(!page.contains("<video"));
+
+        let page = read(&dir.join("tweets/2.html"));
+        position(&page, r#"Replying to"#);
+        position(&page, r#"<a href="../tweets/1.html">1</a>"#);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
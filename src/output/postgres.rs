@@ -0,0 +1,505 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use chrono::Utc;
+use tokio_postgres::{Client, NoTls, Transaction};
+
+use crate::types::{profile::Profile, tweets::Tweet};
+
+const MIGRATIONS: &[(i32, &str, &str)] = &[(
+    1,
+    "initial",
+    include_str!("postgres/migrations/0001_initial.sql"),
+)];
+
+pub(crate) struct Store {
+    client: Client,
+    crawl: i64,
+}
+
+pub(crate) async fn connect(url: &str) -> anyhow::Result<Client> {
+    let (client, connection) = tokio_postgres::connect(url, NoTls)
+        .await
+        .context("connecting to postgres")?;
+
+    tokio::spawn(async move {
+        if let Err(error) = connection.await {
+            eprintln!("postgres connection error: {error}");
+        }
+    });
+
+    Ok(client)
+}
+
+pub(crate) async fn migrate(client: &mut Client) -> anyhow::Result<Vec<&'static str>> {
+    client
+        .batch_execute(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (
+                version INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+        )
+        .await?;
+
+    let mut applied = Vec::new();
+    for &(version, name, sql) in MIGRATIONS {
+        let transaction = client.transaction().await?;
+        transaction
+            .execute("LOCK TABLE schema_migrations IN EXCLUSIVE MODE", &[])
+            .await?;
+
+        let exists = transaction
+            .query_opt(
+                "SELECT 1 FROM schema_migrations WHERE version = $1",
+                &[&version],
+            )
+            .await?
+            .is_some();
+        if exists {
+            continue;
+        }
+
+        transaction
+            .batch_execute(sql)
+            .await
+            .with_context(|| format!("applying migration {version:04} {name}"))?;
+        transaction
+            .execute(
+                "INSERT INTO schema_migrations (version, name) VALUES ($1, $2)",
+                &[&version, &name],
+            )
+            .await?;
+        transaction.commit().await?;
+
+        applied.push(name);
+    }
+
+    Ok(applied)
+}
+
+async fn pending(client: &Client) -> anyhow::Result<usize> {
+    let table = client
+        .query_one("SELECT to_regclass('schema_migrations') IS NOT NULL", &[])
+        .await?;
+    if !table.get::<_, bool>(0) {
+        return Ok(MIGRATIONS.len());
+    }
+
+    let versions = client
+        .query("SELECT version FROM schema_migrations", &[])
+        .await?
+        .iter()
+        .map(|row| row.get::<_, i32>(0))
+        .collect::<Vec<_>>();
+
+    Ok(MIGRATIONS
+        .iter()
+        .filter(|(version, ..)| !versions.contains(version))
+        .count())
+}
+
+impl Store {
+    pub(crate) async fn open(
+        url: &str,
+        kind: &str,
+        query: &str,
+        search_mode: &str,
+    ) -> anyhow::Result<Self> {
+        let client = connect(url).await?;
+
+        let pending = pending(&client).await?;
+        if pending > 0 {
+            anyhow::bail!(
+                "the database has {pending} pending migrations, run `twitter-scraper db migrate` first"
+            );
+        }
+
+        let crawl = client
+            .query_one(
+                "INSERT INTO crawls (kind, query, search_mode, started_at)
+                 VALUES ($1, $2, $3, $4)
+                 RETURNING id",
+                &[&kind, &query, &search_mode, &Utc::now()],
+            )
+            .await?
+            .get(0);
+
+        Ok(Self { client, crawl })
+    }
+
+    pub(crate) async fn tweets(&mut self, tweets: &[Tweet]) -> anyhow::Result<()> {
+        let tweets = tweets
+            .iter()
+            .filter_map(|tweet| Some((tweet.id.as_deref()?, tweet)))
+            .collect::<BTreeMap<_, _>>();
+        if tweets.is_empty() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let transaction = self.client.transaction().await?;
+
+        let authors = tweets
+            .values()
+            .filter_map(|tweet| Some((tweet.user_id.as_deref()?, *tweet)))
+            .collect::<BTreeMap<_, _>>();
+        transaction
+            .execute(
+                "INSERT INTO users (id, username, name, first_seen, last_seen)
+                 SELECT *, $4::timestamptz, $4::timestamptz
+                 FROM unnest($1::text[], $2::text[], $3::text[])
+                 ON CONFLICT (id) DO UPDATE SET
+                    username = coalesce(excluded.username, users.username),
+                    name = coalesce(excluded.name, users.name),
+                    last_seen = excluded.last_seen",
+                &[
+                    &authors.keys().collect::<Vec<_>>(),
+                    &column(authors.values(), |tweet| tweet.username.as_deref()),
+                    &column(authors.values(), |tweet| tweet.name.as_deref()),
+                    &now,
+                ],
+            )
+            .await?;
+
+        let rows = tweets.values().copied();
+        transaction
+            .execute(
+                "INSERT INTO tweets (
+                    id, user_id, text, created_at, conversation_id, in_reply_to_status_id,
+                    quoted_status_id, retweeted_status_id, is_reply, is_retweet, is_quoted,
+                    sensitive_content, permanent_url, likes, retweets, replies, quotes, views,
+                    bookmarks, first_seen, last_seen
+                 )
+                 SELECT *, $20::timestamptz, $20::timestamptz
+                 FROM unnest(
+                    $1::text[], $2::text[], $3::text[], $4::timestamptz[], $5::text[],
+                    $6::text[], $7::text[], $8::text[], $9::boolean[], $10::boolean[],
+                    $11::boolean[], $12::boolean[], $13::text[], $14::integer[], $15::integer[],
+                    $16::integer[], $17::integer[], $18::integer[], $19::integer[]
+                 )
+                 ON CONFLICT (id) DO UPDATE SET
+                    text = coalesce(excluded.text, tweets.text),
+                    likes = coalesce(excluded.likes, tweets.likes),
+                    retweets = coalesce(excluded.retweets, tweets.retweets),
+                    replies = coalesce(excluded.replies, tweets.replies),
+                    quotes = coalesce(excluded.quotes, tweets.quotes),
+                    views = coalesce(excluded.views, tweets.views),
+                    bookmarks = coalesce(excluded.bookmarks, tweets.bookmarks),
+                    last_seen = excluded.last_seen",
+                &[
+                    &tweets.keys().collect::<Vec<_>>(),
+                    &column(rows.clone(), |tweet| tweet.user_id.as_deref()),
+                    &column(rows.clone(), |tweet| tweet.text.as_deref()),
+                    &column(rows.clone(), |tweet| tweet.time_parsed),
+                    &column(rows.clone(), |tweet| tweet.conversation_id.as_deref()),
+                    &column(rows.clone(), |tweet| tweet.in_reply_to_status_id.as_deref()),
+                    &column(rows.clone(), |tweet| tweet.quoted_status_id.as_deref()),
+                    &column(rows.clone(), |tweet| tweet.retweeted_status_id.as_deref()),
+                    &column(rows.clone(), |tweet| tweet.is_reply),
+                    &column(rows.clone(), |tweet| tweet.is_retweet),
+                    &column(rows.clone(), |tweet| tweet.is_quoted),
+                    &column(rows.clone(), |tweet| tweet.sensitive_content),
+                    &column(rows.clone(), |tweet| tweet.permanent_url.as_deref()),
+                    &column(rows.clone(), |tweet| tweet.likes),
+                    &column(rows.clone(), |tweet| tweet.retweets.or(tweet.retweet_count)),
+                    &column(rows.clone(), |tweet| tweet.replies.or(tweet.reply_count)),
+                    &column(rows.clone(), |tweet| tweet.quote_count),
+                    &column(rows.clone(), |tweet| tweet.views.or(tweet.ext_views)),
+                    &column(rows, |tweet| tweet.bookmark_count),
+                    &now,
+                ],
+            )
+            .await?;
+
+        children(&transaction, &tweets).await?;
+
+        transaction
+            .execute(
+                "INSERT INTO crawl_tweets (crawl_id, tweet_id)
+                 SELECT $1, unnest($2::text[])
+                 ON CONFLICT DO NOTHING",
+                &[&self.crawl, &tweets.keys().collect::<Vec<_>>()],
+            )
+            .await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub(crate) async fn profiles(&mut self, profiles: &[Profile]) -> anyhow::Result<()> {
+        let profiles = profiles
+            .iter()
+            .map(|profile| (profile.id.as_str(), profile))
+            .collect::<BTreeMap<_, _>>();
+        if profiles.is_empty() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let transaction = self.client.transaction().await?;
+
+        let rows = profiles.values().copied();
+        transaction
+            .execute(
+                "INSERT INTO users (
+                    id, username, name, description, location, url, created_at,
+                    followers_count, following_count, tweets_count, listed_count, protected,
+                    verified, is_blue_verified, profile_image_url, profile_banner_url,
+                    pinned_tweet_id, first_seen, last_seen
+                 )
+                 SELECT *, $18::timestamptz, $18::timestamptz
+                 FROM unnest(
+                    $1::text[], $2::text[], $3::text[], $4::text[], $5::text[], $6::text[],
+                    $7::timestamptz[], $8::integer[], $9::integer[], $10::integer[],
+                    $11::integer[], $12::boolean[], $13::boolean[], $14::boolean[], $15::text[],
+                    $16::text[], $17::text[]
+                 )
+                 ON CONFLICT (id) DO UPDATE SET
+                    username = excluded.username,
+                    name = excluded.name,
+                    description = excluded.description,
+                    location = excluded.location,
+                    url = excluded.url,
+                    created_at = excluded.created_at,
+                    followers_count = excluded.followers_count,
+                    following_count = excluded.following_count,
+                    tweets_count = excluded.tweets_count,
+                    listed_count = excluded.listed_count,
+                    protected = excluded.protected,
+                    verified = excluded.verified,
+                    is_blue_verified = excluded.is_blue_verified,
+                    profile_image_url = excluded.profile_image_url,
+                    profile_banner_url = excluded.profile_banner_url,
+                    pinned_tweet_id = excluded.pinned_tweet_id,
+                    last_seen = excluded.last_seen",
+                &[
+                    &profiles.keys().collect::<Vec<_>>(),
+                    &column(rows.clone(), |profile| profile.username.as_str()),
+                    &column(rows.clone(), |profile| profile.name.as_str()),
+                    &column(rows.clone(), |profile| profile.description.as_deref()),
+                    &column(rows.clone(), |profile| profile.location.as_deref()),
+                    &column(rows.clone(), |profile| profile.url.as_deref()),
+                    &column(rows.clone(), |profile| profile.created_at),
+                    &column(rows.clone(), |profile| profile.followers_count),
+                    &column(rows.clone(), |profile| profile.following_count),
+                    &column(rows.clone(), |profile| profile.tweets_count),
+                    &column(rows.clone(), |profile| profile.listed_count),
+                    &column(rows.clone(), |profile| profile.protected),
+                    &column(rows.clone(), |profile| profile.verified),
+                    &column(rows.clone(), |profile| profile.is_blue_verified),
+                    &column(rows.clone(), |profile| profile.profile_image_url.as_deref()),
+                    &column(rows.clone(), |profile| {
+                        profile.profile_banner_url.as_deref()
+                    }),
+                    &column(rows, |profile| profile.pinned_tweet_id.as_deref()),
+                    &now,
+                ],
+            )
+            .await?;
+
+        transaction
+            .execute(
+                "INSERT INTO crawl_users (crawl_id, user_id)
+                 SELECT $1, unnest($2::text[])
+                 ON CONFLICT DO NOTHING",
+                &[&self.crawl, &profiles.keys().collect::<Vec<_>>()],
+            )
+            .await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub(crate) async fn finish(self) -> anyhow::Result<()> {
+        self.client
+            .execute(
+                "UPDATE crawls SET finished_at = $1 WHERE id = $2",
+                &[&Utc::now(), &self.crawl],
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+async fn children(
+    transaction: &Transaction<'_>,
+    tweets: &BTreeMap<&str, &Tweet>,
+) -> anyhow::Result<()> {
+    let mut media = BTreeMap::new();
+    for (id, tweet) in tweets {
+        for photo in &tweet.photos {
+            let row = (
+                "photo",
+                Some(photo.url.as_str()),
+                None,
+                photo.alt_text.as_deref(),
+            );
+            media.insert((*id, photo.id.as_str()), row);
+        }
+        for video in &tweet.videos {
+            let row = (
+                "video",
+                video.url.as_deref(),
+                Some(video.preview.as_str()),
+                None,
+            );
+            media.insert((*id, video.id.as_str()), row);
+        }
+    }
+
+    transaction
+        .execute(
+            "INSERT INTO media (tweet_id, id, kind, url, preview, alt_text)
+             SELECT * FROM unnest($1::text[], $2::text[], $3::text[], $4::text[], $5::text[], $6::text[])
+             ON CONFLICT (tweet_id, id) DO UPDATE SET
+                url = excluded.url,
+                preview = excluded.preview,
+                alt_text = excluded.alt_text",
+            &[
+                &column(media.keys(), |(tweet, _)| *tweet),
+                &column(media.keys(), |(_, id)| *id),
+                &column(media.values(), |row| row.0),
+                &column(media.values(), |row| row.1),
+                &column(media.values(), |row| row.2),
+                &column(media.values(), |row| row.3),
+            ],
+        )
+        .await?;
+
+    let mentions = tweets
+        .iter()
+        .flat_map(|(id, tweet)| tweet.mentions.iter().map(move |mention| (*id, mention)))
+        .collect::<Vec<_>>();
+    transaction
+        .execute(
+            "INSERT INTO mentions (tweet_id, user_id, username, name)
+             SELECT * FROM unnest($1::text[], $2::text[], $3::text[], $4::text[])
+             ON CONFLICT DO NOTHING",
+            &[
+                &column(&mentions, |(id, _)| *id),
+                &column(&mentions, |(_, mention)| mention.id.as_str()),
+                &column(&mentions, |(_, mention)| mention.username.as_deref()),
+                &column(&mentions, |(_, mention)| mention.name.as_deref()),
+            ],
+        )
+        .await?;
+
+    let hashtags = tweets
+        .iter()
+        .flat_map(|(id, tweet)| tweet.hashtags.iter().map(move |hashtag| (*id, hashtag)))
+        .collect::<Vec<_>>();
+    transaction
+        .execute(
+            "INSERT INTO hashtags (tweet_id, hashtag)
+             SELECT * FROM unnest($1::text[], $2::text[])
+             ON CONFLICT DO NOTHING",
+            &[
+                &column(&hashtags, |(id, _)| *id),
+                &column(&hashtags, |(_, hashtag)| hashtag.as_str()),
+            ],
+        )
+        .await?;
+
+    let urls = tweets
+        .iter()
+        .flat_map(|(id, tweet)| tweet.urls.iter().map(move |url| (*id, url)))
+        .collect::<Vec<_>>();
+    transaction
+        .execute(
+            "INSERT INTO urls (tweet_id, url)
+             SELECT * FROM unnest($1::text[], $2::text[])
+             ON CONFLICT DO NOTHING",
+            &[
+                &column(&urls, |(id, _)| *id),
+                &column(&urls, |(_, url)| url.as_str()),
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+fn column<I: IntoIterator, U>(rows: I, value: impl Fn(I::Item) -> U) -> Vec<U> {
+    rows.into_iter().map(value).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tweet(id: &str, likes: i32, views: Option<i32>) -> Tweet {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "user_id": "1",
+            "username": "rustlang",
+            "text": "Rust 1.0",
+            "likes": likes,
+            "views": views,
+            "hashtags": ["rust", "rust"],
+            "urls": ["https://rust-lang.org"],
+            "mentions": [{ "id": "2", "username": "ferris" }],
+            "photos": [{ "id": "p1", "url": "https://pbs.twimg.com/media/p1.jpg" }],
+            "videos": [],
+            "thread": [],
+        }))
+        .unwrap()
+    }
+
+    async fn count(client: &Client, sql: &str) -> i64 {
+        client.query_one(sql, &[]).await.unwrap().get(0)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a disposable database in TWITTER_SCRAPER_TEST_DATABASE_URL"]
+    async fn upserts_into_local_postgres() {
+        let url = std::env::var("TWITTER_SCRAPER_TEST_DATABASE_URL").unwrap();
+        let mut client = connect(&url).await.unwrap();
+        client
+            .batch_execute("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+            .await
+            .unwrap();
+
+        assert!(Store::open(&url, "tweets", "rust", "latest").await.is_err());
+        assert_eq!(migrate(&mut client).await.unwrap(), ["initial"]);
+        assert!(migrate(&mut client).await.unwrap().is_empty());
+
+        let mut store = Store::open(&url, "tweets", "rust", "latest").await.unwrap();
+        store
+            .tweets(&[tweet("10", 1, Some(100)), tweet("11", 2, Some(200))])
+            .await
+            .unwrap();
+        store.finish().await.unwrap();
+
+        let mut store = Store::open(&url, "tweets", "rust", "top").await.unwrap();
+        store.tweets(&[tweet("10", 5, None)]).await.unwrap();
+        store.finish().await.unwrap();
+
+        let row = client
+            .query_one(
+                "SELECT likes, views, first_seen < last_seen FROM tweets WHERE id = '10'",
+                &[],
+            )
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, Option<i32>>(0), Some(5));
+        assert_eq!(row.get::<_, Option<i32>>(1), Some(100));
+        assert!(row.get::<_, bool>(2));
+
+        assert_eq!(count(&client, "SELECT count(*) FROM tweets").await, 2);
+        assert_eq!(count(&client, "SELECT count(*) FROM users").await, 1);
+        assert_eq!(count(&client, "SELECT count(*) FROM hashtags").await, 2);
+        assert_eq!(count(&client, "SELECT count(*) FROM media").await, 2);
+        assert_eq!(count(&client, "SELECT count(*) FROM mentions").await, 2);
+        assert_eq!(count(&client, "SELECT count(*) FROM crawl_tweets").await, 3);
+        assert_eq!(
+            count(
+                &client,
+                "SELECT count(*) FROM crawls WHERE finished_at IS NOT NULL"
+            )
+            .await,
+            2
+        );
+    }
+}
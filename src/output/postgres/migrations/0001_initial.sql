@@ -0,0 +1,102 @@
+CREATE TABLE crawls (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    kind TEXT NOT NULL,
+    query TEXT NOT NULL,
+    search_mode TEXT NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ
+);
+
+CREATE TABLE users (
+    id TEXT PRIMARY KEY,
+    username TEXT,
+    name TEXT,
+    description TEXT,
+    location TEXT,
+    url TEXT,
+    created_at TIMESTAMPTZ,
+    followers_count INTEGER,
+    following_count INTEGER,
+    tweets_count INTEGER,
+    listed_count INTEGER,
+    protected BOOLEAN,
+    verified BOOLEAN,
+    is_blue_verified BOOLEAN,
+    profile_image_url TEXT,
+    profile_banner_url TEXT,
+    pinned_tweet_id TEXT,
+    first_seen TIMESTAMPTZ NOT NULL,
+    last_seen TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE tweets (
+    id TEXT PRIMARY KEY,
+    user_id TEXT REFERENCES users (id),
+    text TEXT,
+    created_at TIMESTAMPTZ,
+    conversation_id TEXT,
+    in_reply_to_status_id TEXT,
+    quoted_status_id TEXT,
+    retweeted_status_id TEXT,
+    is_reply BOOLEAN,
+    is_retweet BOOLEAN,
+    is_quoted BOOLEAN,
+    sensitive_content BOOLEAN,
+    permanent_url TEXT,
+    likes INTEGER,
+    retweets INTEGER,
+    replies INTEGER,
+    quotes INTEGER,
+    views INTEGER,
+    bookmarks INTEGER,
+    first_seen TIMESTAMPTZ NOT NULL,
+    last_seen TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX tweets_user_id ON tweets (user_id);
+CREATE INDEX tweets_created_at ON tweets (created_at);
+CREATE INDEX tweets_conversation_id ON tweets (conversation_id);
+
+CREATE TABLE media (
+    tweet_id TEXT NOT NULL REFERENCES tweets (id),
+    id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    url TEXT,
+    preview TEXT,
+    alt_text TEXT,
+    PRIMARY KEY (tweet_id, id)
+);
+
+CREATE TABLE mentions (
+    tweet_id TEXT NOT NULL REFERENCES tweets (id),
+    user_id TEXT NOT NULL,
+    username TEXT,
+    name TEXT,
+    PRIMARY KEY (tweet_id, user_id)
+);
+
+CREATE TABLE hashtags (
+    tweet_id TEXT NOT NULL REFERENCES tweets (id),
+    hashtag TEXT NOT NULL,
+    PRIMARY KEY (tweet_id, hashtag)
+);
+
+CREATE INDEX hashtags_hashtag ON hashtags (hashtag);
+
+CREATE TABLE urls (
+    tweet_id TEXT NOT NULL REFERENCES tweets (id),
+    url TEXT NOT NULL,
+    PRIMARY KEY (tweet_id, url)
+);
+
+CREATE TABLE crawl_tweets (
+    crawl_id BIGINT NOT NULL REFERENCES crawls (id),
+    tweet_id TEXT NOT NULL REFERENCES tweets (id),
+    PRIMARY KEY (crawl_id, tweet_id)
+);
+
+CREATE TABLE crawl_users (
+    crawl_id BIGINT NOT NULL REFERENCES crawls (id),
+    user_id TEXT NOT NULL REFERENCES users (id),
+    PRIMARY KEY (crawl_id, user_id)
+);
@@ -0,0 +1,420 @@
+use std::path::Path;
+
+use anyhow::Context as _;
+use chrono::Utc;
+use rusqlite::{params, Connection, Transaction};
+
+use crate::types::{profile::Profile, tweets::Tweet};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS crawls (
+    id INTEGER PRIMARY KEY,
+    kind TEXT NOT NULL,
+    query TEXT NOT NULL,
+    search_mode TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    finished_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    username TEXT,
+    name TEXT,
+    description TEXT,
+    location TEXT,
+    url TEXT,
+    created_at TEXT,
+    followers_count INTEGER,
+    following_count INTEGER,
+    tweets_count INTEGER,
+    listed_count INTEGER,
+    protected INTEGER,
+    verified INTEGER,
+    is_blue_verified INTEGER,
+    profile_image_url TEXT,
+    profile_banner_url TEXT,
+    pinned_tweet_id TEXT,
+    first_seen TEXT NOT NULL,
+    last_seen TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tweets (
+    id TEXT PRIMARY KEY,
+    user_id TEXT REFERENCES users (id),
+    text TEXT,
+    created_at TEXT,
+    conversation_id TEXT,
+    in_reply_to_status_id TEXT,
+    quoted_status_id TEXT,
+    retweeted_status_id TEXT,
+    is_reply INTEGER,
+    is_retweet INTEGER,
+    is_quoted INTEGER,
+    sensitive_content INTEGER,
+    permanent_url TEXT,
+    likes INTEGER,
+    retweets INTEGER,
+    replies INTEGER,
+    quotes INTEGER,
+    views INTEGER,
+    bookmarks INTEGER,
+    first_seen TEXT NOT NULL,
+    last_seen TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS media (
+    tweet_id TEXT NOT NULL REFERENCES tweets (id),
+    id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    url TEXT,
+    preview TEXT,
+    alt_text TEXT,
+    PRIMARY KEY (tweet_id, id)
+);
+
+CREATE TABLE IF NOT EXISTS mentions (
+    tweet_id TEXT NOT NULL REFERENCES tweets (id),
+    user_id TEXT NOT NULL,
+    username TEXT,
+    name TEXT,
+    PRIMARY KEY (tweet_id, user_id)
+);
+
+CREATE TABLE IF NOT EXISTS hashtags (
+    tweet_id TEXT NOT NULL REFERENCES tweets (id),
+    hashtag TEXT NOT NULL,
+    PRIMARY KEY (tweet_id, hashtag)
+);
+
+CREATE TABLE IF NOT EXISTS urls (
+    tweet_id TEXT NOT NULL REFERENCES tweets (id),
+    url TEXT NOT NULL,
+    PRIMARY KEY (tweet_id, url)
+);
+
+CREATE TABLE IF NOT EXISTS crawl_tweets (
+    crawl_id INTEGER NOT NULL REFERENCES crawls (id),
+    tweet_id TEXT NOT NULL REFERENCES tweets (id),
+    PRIMARY KEY (crawl_id, tweet_id)
+);
+
+CREATE TABLE IF NOT EXISTS crawl_users (
+    crawl_id INTEGER NOT NULL REFERENCES crawls (id),
+    user_id TEXT NOT NULL REFERENCES users (id),
+    PRIMARY KEY (crawl_id, user_id)
+);
+";
+
+pub(crate) struct Store {
+    connection: Connection,
+    crawl: i64,
+}
+
+impl Store {
+    pub(crate) fn open(
+        path: &Path,
+        kind: &str,
+        query: &str,
+        search_mode: &str,
+    ) -> anyhow::Result<Self> {
+        let connection =
+            Connection::open(path).with_context(|| format!("opening `{}`", path.display()))?;
+
+        Self::start(connection, kind, query, search_mode)
+    }
+
+    fn start(
+        connection: Connection,
+        kind: &str,
+        query: &str,
+        search_mode: &str,
+    ) -> anyhow::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+
+        connection.execute(
+            "INSERT INTO crawls (kind, query, search_mode, started_at) VALUES (?1, ?2, ?3, ?4)",
+            params![kind, query, search_mode, Utc::now().to_rfc3339()],
+        )?;
+        let crawl = connection.last_insert_rowid();
+
+        Ok(Self { connection, crawl })
+    }
+
+    pub(crate) fn tweets(&mut self, tweets: &[Tweet]) -> anyhow::Result<()> {
+        let now = Utc::now().to_rfc3339();
+        let transaction = self.connection.transaction()?;
+
+        for tweet in tweets {
+            let Some(id) = &tweet.id else {
+                continue;
+            };
+
+            if let Some(user_id) = &tweet.user_id {
+                transaction.execute(
+                    "INSERT INTO users (id, username, name, first_seen, last_seen)
+                     VALUES (?1, ?2, ?3, ?4, ?4)
+                     ON CONFLICT (id) DO UPDATE SET
+                        username = coalesce(excluded.username, username),
+                        name = coalesce(excluded.name, name),
+                        last_seen = excluded.last_seen",
+                    params![user_id, tweet.username, tweet.name, now],
+                )?;
+            }
+
+            transaction.execute(
+                "INSERT INTO tweets (
+                    id, user_id, text, created_at, conversation_id, in_reply_to_status_id,
+                    quoted_status_id, retweeted_status_id, is_reply, is_retweet, is_quoted,
+                    sensitive_content, permanent_url, likes, retweets, replies, quotes, views,
+                    bookmarks, first_seen, last_seen
+                 )
+                 VALUES (
+                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                    ?18, ?19, ?20, ?20
+                 )
+                 ON CONFLICT (id) DO UPDATE SET
+                    text = coalesce(excluded.text, text),
+                    likes = coalesce(excluded.likes, likes),
+                    retweets = coalesce(excluded.retweets, retweets),
+                    replies = coalesce(excluded.replies, replies),
+                    quotes = coalesce(excluded.quotes, quotes),
+                    views = coalesce(excluded.views, views),
+                    bookmarks = coalesce(excluded.bookmarks, bookmarks),
+                    last_seen = excluded.last_seen",
+                params![
+                    id,
+                    tweet.user_id,
+                    tweet.text,
+                    tweet.time_parsed.map(|time| time.to_rfc3339()),
+                    tweet.conversation_id,
+                    tweet.in_reply_to_status_id,
+                    tweet.quoted_status_id,
+                    tweet.retweeted_status_id,
+                    tweet.is_reply,
+                    tweet.is_retweet,
+                    tweet.is_quoted,
+                    tweet.sensitive_content,
+                    tweet.permanent_url,
+                    tweet.likes,
+                    tweet.retweets.or(tweet.retweet_count),
+                    tweet.replies.or(tweet.reply_count),
+                    tweet.quote_count,
+                    tweet.views.or(tweet.ext_views),
+                    tweet.bookmark_count,
+                    now,
+                ],
+            )?;
+
+            children(&transaction, id, tweet)?;
+
+            transaction.execute(
+                "INSERT OR IGNORE INTO crawl_tweets (crawl_id, tweet_id) VALUES (?1, ?2)",
+                params![self.crawl, id],
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub(crate) fn profiles(&mut self, profiles: &[Profile]) -> anyhow::Result<()> {
+        let now = Utc::now().to_rfc3339();
+        let transaction = self.connection.transaction()?;
+
+        for profile in profiles {
+            transaction.execute(
+                "INSERT INTO users (
+                    id, username, name, description, location, url, created_at,
+                    followers_count, following_count, tweets_count, listed_count, protected,
+                    verified, is_blue_verified, profile_image_url, profile_banner_url,
+                    pinned_tweet_id, first_seen, last_seen
+                 )
+                 VALUES (
+                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                    ?18, ?18
+                 )
+                 ON CONFLICT (id) DO UPDATE SET
+                    username = excluded.username,
+                    name = excluded.name,
+                    description = excluded.description,
+                    location = excluded.location,
+                    url = excluded.url,
+                    created_at = excluded.created_at,
+                    followers_count = excluded.followers_count,
+                    following_count = excluded.following_count,
+                    tweets_count = excluded.tweets_count,
+                    listed_count = excluded.listed_count,
+                    protected = excluded.protected,
+                    verified = excluded.verified,
+                    is_blue_verified = excluded.is_blue_verified,
+                    profile_image_url = excluded.profile_image_url,
+                    profile_banner_url = excluded.profile_banner_url,
+                    pinned_tweet_id = excluded.pinned_tweet_id,
+                    last_seen = excluded.last_seen",
+                params![
+                    profile.id,
+                    profile.username,
+                    profile.name,
+                    profile.description,
+                    profile.location,
+                    profile.url,
+                    profile.created_at.to_rfc3339(),
+                    profile.followers_count,
+                    profile.following_count,
+                    profile.tweets_count,
+                    profile.listed_count,
+                    profile.protected,
+                    profile.verified,
+                    profile.is_blue_verified,
+                    profile.profile_image_url,
+                    profile.profile_banner_url,
+                    profile.pinned_tweet_id,
+                    now,
+                ],
+            )?;
+
+            transaction.execute(
+                "INSERT OR IGNORE INTO crawl_users (crawl_id, user_id) VALUES (?1, ?2)",
+                params![self.crawl, profile.id],
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> anyhow::Result<()> {
+        self.connection.execute(
+            "UPDATE crawls SET finished_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), self.crawl],
+        )?;
+
+        Ok(())
+    }
+}
+
+fn children(transaction: &Transaction<'_>, id: &str, tweet: &Tweet) -> anyhow::Result<()> {
+    for photo in &tweet.photos {
+        transaction.execute(
+            "INSERT INTO media (tweet_id, id, kind, url, alt_text) VALUES (?1, ?2, 'photo', ?3, ?4)
+             ON CONFLICT (tweet_id, id) DO UPDATE SET url = excluded.url, alt_text = excluded.alt_text",
+            params![id, photo.id, photo.url, photo.alt_text],
+        )?;
+    }
+
+    for video in &tweet.videos {
+        transaction.execute(
+            "INSERT INTO media (tweet_id, id, kind, url, preview) VALUES (?1, ?2, 'video', ?3, ?4)
+             ON CONFLICT (tweet_id, id) DO UPDATE SET url = excluded.url, preview = excluded.preview",
+            params![id, video.id, video.url, video.preview],
+        )?;
+    }
+
+    for mention in &tweet.mentions {
+        transaction.execute(
+            "INSERT OR IGNORE INTO mentions (tweet_id, user_id, username, name) VALUES (?1, ?2, ?3, ?4)",
+            params![id, mention.id, mention.username, mention.name],
+        )?;
+    }
+
+    for hashtag in &tweet.hashtags {
+        transaction.execute(
+            "INSERT OR IGNORE INTO hashtags (tweet_id, hashtag) VALUES (?1, ?2)",
+            params![id, hashtag],
+        )?;
+    }
+
+    for url in &tweet.urls {
+        transaction.execute(
+            "INSERT OR IGNORE INTO urls (tweet_id, url) VALUES (?1, ?2)",
+            params![id, url],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tweet(likes: i32, views: i32) -> Tweet {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "user_id": "7",
+            "username": "rustlang",
+            "text": "Rust 1.79 is out",
+            "likes": likes,
+            "views": views,
+            "hashtags": ["rust"],
+            "urls": [],
+            "mentions": [],
+            "photos": [],
+            "videos": [],
+            "thread": [],
+        }))
+        .unwrap()
+    }
+
+    fn count(store: &Store, table: &str) -> i64 {
+        store
+            .connection
+            .query_row(&format!("SELECT count(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    fn seen(store: &Store) -> (String, String) {
+        store
+            .connection
+            .query_row(
+                "SELECT first_seen, last_seen FROM tweets WHERE id = '1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn rewriting_a_tweet_updates_its_row() {
+        let connection = Connection::open_in_memory().unwrap();
+        let mut store = Store::start(connection, "tweets", "rust", "Latest").unwrap();
+
+        store.tweets(&[tweet(3, 100)]).unwrap();
+        let (first_seen, _) = seen(&store);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        store.tweets(&[tweet(5, 250)]).unwrap();
+
+        let (again, last_seen) = seen(&store);
+        assert_eq!(again, first_seen);
+        assert!(last_seen > first_seen, "{last_seen} <= {first_seen}");
+
+        assert_eq!(count(&store, "tweets"), 1);
+        assert_eq!(count(&store, "users"), 1);
+        assert_eq!(count(&store, "hashtags"), 1);
+        assert_eq!(count(&store, "crawl_tweets"), 1);
+
+        let (likes, views, text): (i32, i32, String) = store
+            .connection
+            .query_row(
+                "SELECT likes, views, text FROM tweets WHERE id = '1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((likes, views, text.as_str()), (5, 250, "Rust 1.79 is out"));
+
+        let (kind, query, search_mode): (String, String, String) = store
+            .connection
+            .query_row("SELECT kind, query, search_mode FROM crawls", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(
+            (kind.as_str(), query.as_str(), search_mode.as_str()),
+            ("tweets", "rust", "Latest")
+        );
+        assert_eq!(count(&store, "crawls"), 1);
+    }
+}
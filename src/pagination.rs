@@ -0,0 +1,465 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use futures::{future::BoxFuture, FutureExt as _, Stream};
+
+use crate::types::Entry;
+
+const MAX_PAGE_SIZE: u32 = 50;
+const MAX_EMPTY_PAGES: u32 = 3;
+
+pub(crate) struct Page<T> {
+    pub(crate) items: Vec<T>,
+    pub(crate) next: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StopReason {
+    Exhausted,
+    RepeatedCursor,
+    EmptyPages(u32),
+    Limit,
+    Since,
+    TimeBudget,
+}
+
+impl StopReason {
+    pub(crate) fn is_final(self) -> bool {
+        !matches!(self, Self::Limit | Self::TimeBudget)
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exhausted => f.write_str("no more pages"),
+            Self::RepeatedCursor => f.write_str("the next cursor was already visited"),
+            Self::EmptyPages(count) => write!(f, "{count} consecutive pages had no new entries"),
+            Self::Limit => f.write_str("the item limit was reached"),
+            Self::Since => f.write_str("entries went past the `since` bound"),
+            Self::TimeBudget => f.write_str("the time budget ran out"),
+        }
+    }
+}
+
+type Fetch<'a, T> =
+    Box<dyn FnMut(Option<String>, u32) -> BoxFuture<'a, anyhow::Result<Page<T>>> + Send + 'a>;
+
+pub(crate) struct Pagination<'a, T> {
+    fetch: Fetch<'a, T>,
+    page_size: u32,
+    cursor: Option<String>,
+    pending: Option<BoxFuture<'a, anyhow::Result<Page<T>>>>,
+    buffer: VecDeque<T>,
+    seen_cursors: HashSet<String>,
+    seen_ids: HashSet<String>,
+    duplicates: usize,
+    empty_pages: u32,
+    max_empty_pages: u32,
+    limit: Option<usize>,
+    emitted: usize,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    chronological: bool,
+    max_duration: Option<Duration>,
+    started: Option<Instant>,
+    done: bool,
+    stop_reason: Option<StopReason>,
+}
+
+impl<'a, T: Entry> Pagination<'a, T> {
+    pub(crate) fn new(
+        fetch: impl FnMut(Option<String>, u32) -> BoxFuture<'a, anyhow::Result<Page<T>>> + Send + 'a,
+    ) -> Self {
+        Self {
+            fetch: Box::new(fetch),
+            page_size: MAX_PAGE_SIZE,
+            cursor: None,
+            pending: None,
+            buffer: VecDeque::new(),
+            seen_cursors: HashSet::new(),
+            seen_ids: HashSet::new(),
+            duplicates: 0,
+            empty_pages: 0,
+            max_empty_pages: MAX_EMPTY_PAGES,
+            limit: None,
+            emitted: 0,
+            since: None,
+            until: None,
+            chronological: false,
+            max_duration: None,
+            started: None,
+            done: false,
+            stop_reason: None,
+        }
+    }
+
+    pub(crate) fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.min(MAX_PAGE_SIZE);
+        self
+    }
+
+    pub(crate) fn cursor(mut self, cursor: Option<String>) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    pub(crate) fn max_empty_pages(mut self, max_empty_pages: u32) -> Self {
+        self.max_empty_pages = max_empty_pages.max(1);
+        self
+    }
+
+    pub(crate) fn limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub(crate) fn since(mut self, since: Option<DateTime<Utc>>) -> Self {
+        self.since = since;
+        self
+    }
+
+    pub(crate) fn until(mut self, until: Option<DateTime<Utc>>) -> Self {
+        self.until = until;
+        self
+    }
+
+    pub(crate) fn chronological(mut self, chronological: bool) -> Self {
+        self.chronological = chronological;
+        self
+    }
+
+    pub(crate) fn max_duration(mut self, max_duration: Option<Duration>) -> Self {
+        self.max_duration = max_duration;
+        self
+    }
+
+    pub(crate) fn seen(mut self, ids: impl IntoIterator<Item = String>) -> Self {
+        self.seen_ids.extend(ids);
+        self
+    }
+
+    pub(crate) fn stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason
+    }
+
+    pub(crate) fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    pub(crate) fn next_cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    pub(crate) async fn next_page(&mut self) -> anyhow::Result<Option<Vec<T>>> {
+        std::future::poll_fn(|cx| self.poll_page(cx))
+            .await
+            .transpose()
+    }
+
+    fn poll_page(&mut self, cx: &mut Context<'_>) -> Poll<Option<anyhow::Result<Vec<T>>>> {
+        loop {
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            let started = *self.started.get_or_insert_with(Instant::now);
+            if self.pending.is_none()
+                && self
+                    .max_duration
+                    .is_some_and(|max_duration| started.elapsed() >= max_duration)
+            {
+                self.stop(StopReason::TimeBudget);
+                continue;
+            }
+
+            let page_size = self.page_size;
+            let pending = self
+                .pending
+                .get_or_insert_with(|| (self.fetch)(self.cursor.clone(), page_size));
+
+            let result = ready!(pending.poll_unpin(cx));
+            self.pending = None;
+
+            let page = match result {
+                Ok(page) => page,
+                Err(error) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(error)));
+                }
+            };
+
+            let requested = self.cursor.take();
+            if let Some(cursor) = &requested {
+                self.seen_cursors.insert(cursor.clone());
+            }
+
+            match page.next {
+                None => self.stop(StopReason::Exhausted),
+                Some(next) if self.seen_cursors.contains(&next) => {
+                    self.stop(StopReason::RepeatedCursor)
+                }
+                Some(next) => self.cursor = Some(next),
+            }
+
+            let received = page.items.len();
+            let items = page
+                .items
+                .into_iter()
+                .filter(|item| match item.id() {
+                    Some(id) => self.seen_ids.insert(id.to_owned()),
+                    None => true,
+                })
+                .collect::<Vec<_>>();
+            self.duplicates += received - items.len();
+
+            if items.is_empty() {
+                self.empty_pages += 1;
+                if self.empty_pages >= self.max_empty_pages {
+                    self.stop(StopReason::EmptyPages(self.empty_pages));
+                }
+                continue;
+            }
+
+            self.empty_pages = 0;
+
+            let (items, cut) = self.bound(items);
+            if cut {
+                self.cursor = requested;
+            }
+
+            if !items.is_empty() {
+                return Poll::Ready(Some(Ok(items)));
+            }
+        }
+    }
+
+    fn bound(&mut self, items: Vec<T>) -> (Vec<T>, bool) {
+        let mut bounded = Vec::with_capacity(items.len());
+        let mut cut = false;
+
+        for item in items {
+            if let Some(time) = item.time() {
+                if self.until.is_some_and(|until| time >= until) {
+                    continue;
+                }
+
+                if self.since.is_some_and(|since| time < since) {
+                    if self.chronological {
+                        self.stop(StopReason::Since);
+                    }
+                    continue;
+                }
+            }
+
+            if self.limit.is_some_and(|limit| self.emitted >= limit) {
+                self.stop(StopReason::Limit);
+                cut = true;
+                break;
+            }
+
+            self.emitted += 1;
+            bounded.push(item);
+        }
+
+        if self.limit.is_some_and(|limit| self.emitted >= limit) {
+            self.stop(StopReason::Limit);
+        }
+
+        (bounded, cut)
+    }
+
+    fn stop(&mut self, reason: StopReason) {
+        self.done = true;
+        self.stop_reason.get_or_insert(reason);
+    }
+}
+
+impl<T: Entry + Unpin> Stream for Pagination<'_, T> {
+    type Item = anyhow::Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(item) = this.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(item)));
+            }
+
+            match ready!(this.poll_page(cx)) {
+                Some(Ok(items)) => this.buffer.extend(items),
+                Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct Item {
+        id: &'static str,
+        time: Option<DateTime<Utc>>,
+    }
+
+    impl Entry for Item {
+        fn id(&self) -> Option<&str> {
+            Some(self.id)
+        }
+
+        fn time(&self) -> Option<DateTime<Utc>> {
+            self.time
+        }
+
+        fn refresh(&mut self, newer: Self) {
+            *self = newer;
+        }
+    }
+
+    type FakePage = (Option<&'static str>, Vec<Item>, Option<&'static str>);
+
+    fn items(ids: &[&'static str]) -> Vec<Item> {
+        ids.iter().map(|&id| Item { id, time: None }).collect()
+    }
+
+    fn paginate(pages: Vec<FakePage>) -> Pagination<'static, Item> {
+        Pagination::new(move |cursor, _| {
+            let (_, items, next) = pages
+                .iter()
+                .find(|(at, ..)| *at == cursor.as_deref())
+                .cloned()
+                .unwrap_or_else(|| panic!("unexpected cursor {cursor:?}"));
+
+            futures::future::ready(Ok(Page {
+                items,
+                next: next.map(ToOwned::to_owned),
+            }))
+            .boxed()
+        })
+    }
+
+    async fn collect(pagination: &mut Pagination<'_, Item>) -> Vec<Vec<&'static str>> {
+        let mut pages = Vec::new();
+        while let Some(page) = pagination.next_page().await.unwrap() {
+            pages.push(page.iter().map(|item| item.id).collect());
+        }
+        pages
+    }
+
+    #[tokio::test]
+    async fn drops_items_seen_on_earlier_pages() {
+        let mut pagination = paginate(vec![
+            (None, items(&["1", "2", "3"]), Some("a")),
+            (Some("a"), items(&["3", "4", "5"]), Some("b")),
+            (Some("b"), items(&["5", "6"]), None),
+        ])
+        .seen(["2".to_owned()]);
+
+        assert_eq!(
+            collect(&mut pagination).await,
+            [vec!["1", "3"], vec!["4", "5"], vec!["6"]]
+        );
+        assert_eq!(pagination.duplicates(), 3);
+        assert_eq!(pagination.stop_reason(), Some(StopReason::Exhausted));
+    }
+
+    #[tokio::test]
+    async fn stops_on_repeated_cursors() {
+        let mut pagination = paginate(vec![
+            (None, items(&["1"]), Some("a")),
+            (Some("a"), items(&["2"]), Some("b")),
+            (Some("b"), items(&["3"]), Some("a")),
+        ]);
+
+        assert_eq!(
+            collect(&mut pagination).await,
+            [vec!["1"], vec!["2"], vec!["3"]]
+        );
+        assert_eq!(pagination.stop_reason(), Some(StopReason::RepeatedCursor));
+    }
+
+    #[tokio::test]
+    async fn stops_after_runs_of_empty_pages() {
+        let mut pagination = paginate(vec![
+            (None, items(&["1"]), Some("a")),
+            (Some("a"), items(&["1"]), Some("b")),
+            (Some("b"), items(&[]), Some("c")),
+            (Some("c"), items(&["2"]), Some("d")),
+            (Some("d"), items(&[]), Some("e")),
+            (Some("e"), items(&["2"]), Some("f")),
+            (Some("f"), items(&[]), Some("g")),
+        ])
+        .max_empty_pages(3);
+
+        assert_eq!(collect(&mut pagination).await, [vec!["1"], vec!["2"]]);
+        assert_eq!(pagination.stop_reason(), Some(StopReason::EmptyPages(3)));
+    }
+
+    #[tokio::test]
+    async fn limit_counts_unique_items_and_keeps_a_cut_page() {
+        let pages = || {
+            vec![
+                (None, items(&["1", "2", "3"]), Some("a")),
+                (Some("a"), items(&["3", "4", "5"]), Some("b")),
+                (Some("b"), items(&["6"]), None),
+            ]
+        };
+
+        let mut pagination = paginate(pages()).limit(Some(4));
+        assert_eq!(
+            collect(&mut pagination).await,
+            [vec!["1", "2", "3"], vec!["4"]]
+        );
+        assert_eq!(pagination.stop_reason(), Some(StopReason::Limit));
+        assert_eq!(pagination.next_cursor(), Some("a"));
+
+        let mut pagination = paginate(pages()).limit(Some(3));
+        assert_eq!(collect(&mut pagination).await, [vec!["1", "2", "3"]]);
+        assert_eq!(pagination.next_cursor(), Some("a"));
+
+        let mut pagination = paginate(pages()).limit(Some(5));
+        assert_eq!(
+            collect(&mut pagination).await,
+            [vec!["1", "2", "3"], vec!["4", "5"]]
+        );
+        assert_eq!(pagination.next_cursor(), Some("b"));
+    }
+
+    #[tokio::test]
+    async fn keeps_items_inside_the_date_range() {
+        let at = |id, hour: i64| Item {
+            id,
+            time: DateTime::from_timestamp(hour * 3600, 0),
+        };
+        let pages = || {
+            vec![
+                (None, vec![at("10", 10), at("9", 9)], Some("a")),
+                (Some("a"), vec![at("8", 8), at("7", 7)], Some("b")),
+                (Some("b"), vec![at("6", 6), at("5", 5)], Some("c")),
+                (Some("c"), vec![at("4", 4)], None),
+            ]
+        };
+        let since = DateTime::from_timestamp(7 * 3600, 0);
+        let until = DateTime::from_timestamp(10 * 3600, 0);
+
+        let mut pagination = paginate(pages())
+            .since(since)
+            .until(until)
+            .chronological(true);
+        assert_eq!(collect(&mut pagination).await, [vec!["9"], vec!["8", "7"]]);
+        assert_eq!(pagination.stop_reason(), Some(StopReason::Since));
+
+        let mut pagination = paginate(pages()).since(since).until(until);
+        assert_eq!(collect(&mut pagination).await, [vec!["9"], vec!["8", "7"]]);
+        assert_eq!(pagination.stop_reason(), Some(StopReason::Exhausted));
+    }
+}
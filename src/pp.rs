@@ -115,6 +115,9 @@ pub(crate) fn photo(photo: &Photo) {
 
 pub(crate) fn video(video: &Video) {
     println!("  Video: URL: {:?}, ", video.url);
+    if let Some(playlist) = &video.playlist {
+        println!("  Video: Playlist: {playlist}");
+    }
 }
 
 pub(crate) fn poll(poll: &PollV2) {
@@ -0,0 +1,749 @@
+use std::{fmt, str::FromStr, sync::LazyLock};
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use regex::Regex;
+
+static USERNAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[A-Za-z0-9_]{1,15}$").unwrap());
+static LANG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-z]{2,3}(-[A-Za-z]{2,4})?$").unwrap());
+static WITHIN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d+(\.\d+)?(km|mi)$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum Filter {
+    Links,
+    Media,
+    Images,
+    Videos,
+    Replies,
+    Retweets,
+    Quote,
+    Verified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Term {
+    Word(String),
+    Phrase(String),
+    Any(Vec<Self>),
+    All(Vec<Self>),
+    Not(Box<Self>),
+    From(String),
+    To(String),
+    Mention(String),
+    Since(DateTime<Utc>),
+    Until(DateTime<Utc>),
+    MinFaves(u32),
+    MinRetweets(u32),
+    MinReplies(u32),
+    Lang(String),
+    Filter(Filter),
+    Url(String),
+    ConversationId(String),
+    Near(String),
+    Within(String),
+}
+
+impl Term {
+    fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            Self::Word(text) if text.trim().is_empty() => {
+                anyhow::bail!("search terms cannot be empty")
+            }
+            Self::Phrase(text) | Self::Near(text) if text.contains('"') => {
+                anyhow::bail!("`{text}` cannot contain double quotes")
+            }
+            Self::Phrase(text) if text.trim().is_empty() => {
+                anyhow::bail!("phrases cannot be empty")
+            }
+            Self::Any(terms) if terms.len() < 2 => {
+                anyhow::bail!("an OR group needs at least two terms")
+            }
+            Self::All(terms) if terms.len() < 2 => {
+                anyhow::bail!("a group needs at least two terms")
+            }
+            Self::Any(terms) | Self::All(terms) => terms.iter().try_for_each(Self::validate)?,
+            Self::Not(term) => match **term {
+                Self::Not(_) => anyhow::bail!("`{term}` cannot be excluded"),
+                _ => term.validate()?,
+            },
+            Self::From(user) | Self::To(user) | Self::Mention(user) if !USERNAME.is_match(user) => {
+                anyhow::bail!("`{user}` is not a valid username")
+            }
+            Self::Lang(lang) if !LANG.is_match(lang) => {
+                anyhow::bail!("`{lang}` is not a valid language code")
+            }
+            Self::Url(url) if url.is_empty() || url.chars().any(char::is_whitespace) => {
+                anyhow::bail!("`{url}` is not a valid url filter")
+            }
+            Self::ConversationId(id)
+                if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                anyhow::bail!("`{id}` is not a valid conversation id")
+            }
+            Self::Within(radius) if !WITHIN.is_match(radius) => {
+                anyhow::bail!("`{radius}` is not a valid radius, expected e.g. `15mi` or `10km`")
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Word(word) if word.chars().any(char::is_whitespace) => write!(f, "\"{word}\""),
+            Self::Word(word) => f.write_str(word),
+            Self::Phrase(phrase) => write!(f, "\"{phrase}\""),
+            Self::Any(terms) => write_group(f, terms, " OR "),
+            Self::All(terms) => write_group(f, terms, " "),
+            Self::Not(term) => write!(f, "-{term}"),
+            Self::From(user) => write!(f, "from:{user}"),
+            Self::To(user) => write!(f, "to:{user}"),
+            Self::Mention(user) => write!(f, "@{user}"),
+            Self::Since(time) => write_time(f, "since", time),
+            Self::Until(time) => write_time(f, "until", time),
+            Self::MinFaves(count) => write!(f, "min_faves:{count}"),
+            Self::MinRetweets(count) => write!(f, "min_retweets:{count}"),
+            Self::MinReplies(count) => write!(f, "min_replies:{count}"),
+            Self::Lang(lang) => write!(f, "lang:{lang}"),
+            Self::Filter(filter) => write!(f, "filter:{filter}"),
+            Self::Url(url) => write!(f, "url:{url}"),
+            Self::ConversationId(id) => write!(f, "conversation_id:{id}"),
+            Self::Near(place) if place.chars().any(char::is_whitespace) => {
+                write!(f, "near:\"{place}\"")
+            }
+            Self::Near(place) => write!(f, "near:{place}"),
+            Self::Within(radius) => write!(f, "within:{radius}"),
+        }
+    }
+}
+
+fn write_group(f: &mut fmt::Formatter<'_>, terms: &[Term], separator: &str) -> fmt::Result {
+    f.write_str("(")?;
+    for (index, term) in terms.iter().enumerate() {
+        if index > 0 {
+            f.write_str(separator)?;
+        }
+        write!(f, "{term}")?;
+    }
+    f.write_str(")")
+}
+
+fn write_time(f: &mut fmt::Formatter<'_>, operator: &str, time: &DateTime<Utc>) -> fmt::Result {
+    if time.time() == NaiveTime::MIN {
+        write!(f, "{operator}:{}", time.format("%Y-%m-%d"))
+    } else {
+        write!(f, "{operator}_time:{}", time.timestamp())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SearchQuery {
+    terms: Vec<Term>,
+}
+
+impl SearchQuery {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn term(mut self, term: Term) -> Self {
+        self.terms.push(term);
+        self
+    }
+
+    pub(crate) fn phrase(self, phrase: impl Into<String>) -> Self {
+        self.term(Term::Phrase(phrase.into()))
+    }
+
+    pub(crate) fn any(self, terms: impl IntoIterator<Item = Term>) -> Self {
+        self.term(Term::Any(terms.into_iter().collect()))
+    }
+
+    pub(crate) fn exclude(self, term: Term) -> Self {
+        self.term(Term::Not(Box::new(term)))
+    }
+
+    pub(crate) fn authors<'a>(self, users: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut users = users
+            .into_iter()
+            .map(|user| Term::From(strip_at(user)))
+            .collect::<Vec<_>>();
+
+        match users.len() {
+            0 => self,
+            1 => self.term(users.remove(0)),
+            _ => self.term(Term::Any(users)),
+        }
+    }
+
+    pub(crate) fn to(self, user: &str) -> Self {
+        self.term(Term::To(strip_at(user)))
+    }
+
+    pub(crate) fn mention(self, user: &str) -> Self {
+        self.term(Term::Mention(strip_at(user)))
+    }
+
+    pub(crate) fn since(self, time: DateTime<Utc>) -> Self {
+        self.term(Term::Since(time))
+    }
+
+    pub(crate) fn until(self, time: DateTime<Utc>) -> Self {
+        self.term(Term::Until(time))
+    }
+
+    pub(crate) fn min_faves(self, count: u32) -> Self {
+        self.term(Term::MinFaves(count))
+    }
+
+    pub(crate) fn min_retweets(self, count: u32) -> Self {
+        self.term(Term::MinRetweets(count))
+    }
+
+    pub(crate) fn min_replies(self, count: u32) -> Self {
+        self.term(Term::MinReplies(count))
+    }
+
+    pub(crate) fn lang(self, lang: impl Into<String>) -> Self {
+        self.term(Term::Lang(lang.into()))
+    }
+
+    pub(crate) fn filter(self, filter: Filter) -> Self {
+        self.term(Term::Filter(filter))
+    }
+
+    pub(crate) fn exclude_filter(self, filter: Filter) -> Self {
+        self.exclude(Term::Filter(filter))
+    }
+
+    pub(crate) fn url(self, url: impl Into<String>) -> Self {
+        self.term(Term::Url(url.into()))
+    }
+
+    pub(crate) fn conversation_id(self, id: impl Into<String>) -> Self {
+        self.term(Term::ConversationId(id.into()))
+    }
+
+    pub(crate) fn near(self, place: impl Into<String>) -> Self {
+        self.term(Term::Near(place.into()))
+    }
+
+    pub(crate) fn within(self, radius: impl Into<String>) -> Self {
+        self.term(Term::Within(radius.into()))
+    }
+
+    pub(crate) fn with_range(mut self, since: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        self.terms
+            .retain(|term| !matches!(term, Term::Since(_) | Term::Until(_)));
+        self.since(since).until(until)
+    }
+
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        if self.terms.is_empty() {
+            anyhow::bail!("the search query is empty");
+        }
+
+        for term in &self.terms {
+            term.validate()?;
+        }
+
+        if let (Some(since), Some(until)) = (self.since_bound(), self.until_bound()) {
+            if since >= until {
+                anyhow::bail!("`since` ({since}) must be before `until` ({until})");
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn author(&self) -> Option<&str> {
+        let mut authors = self.terms.iter().filter_map(|term| match term {
+            Term::From(user) => Some(user.as_str()),
+            _ => None,
+        });
+
+        let author = authors.next()?;
+        authors.next().is_none().then_some(author)
+    }
+
+    pub(crate) fn since_bound(&self) -> Option<DateTime<Utc>> {
+        self.terms
+            .iter()
+            .filter_map(|term| match term {
+                Term::Since(time) => Some(*time),
+                _ => None,
+            })
+            .max()
+    }
+
+    pub(crate) fn until_bound(&self) -> Option<DateTime<Utc>> {
+        self.terms
+            .iter()
+            .filter_map(|term| match term {
+                Term::Until(time) => Some(*time),
+                _ => None,
+            })
+            .min()
+    }
+}
+
+impl fmt::Display for SearchQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, term) in self.terms.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{term}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for SearchQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { text, position: 0 };
+        let query = Self {
+            terms: parser.sequence(false)?,
+        };
+
+        query.validate()?;
+        Ok(query)
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn sequence(&mut self, grouped: bool) -> anyhow::Result<Vec<Term>> {
+        let mut terms = Vec::new();
+        let mut or = false;
+
+        loop {
+            self.skip_whitespace();
+
+            match self.peek() {
+                None if grouped => anyhow::bail!("unclosed `(` in search query"),
+                None => break,
+                Some(')') if grouped => {
+                    self.bump();
+                    break;
+                }
+                Some(')') => anyhow::bail!("unexpected `)` at position {}", self.position),
+                _ => {}
+            }
+
+            if self.keyword("OR") {
+                if terms.is_empty() || or {
+                    anyhow::bail!("`OR` at position {} has nothing on its left", self.position);
+                }
+                self.position += 2;
+                or = true;
+                continue;
+            }
+
+            let term = self.term()?;
+
+            if !std::mem::take(&mut or) {
+                terms.push(term);
+                continue;
+            }
+
+            let any = match terms.pop() {
+                Some(Term::Any(mut any)) => {
+                    any.push(term);
+                    any
+                }
+                Some(previous) => vec![previous, term],
+                None => anyhow::bail!("`OR` has nothing on its left"),
+            };
+            terms.push(Term::Any(any));
+        }
+
+        if or {
+            anyhow::bail!("`OR` at the end of the search query has nothing on its right");
+        }
+
+        Ok(terms)
+    }
+
+    fn keyword(&self, keyword: &str) -> bool {
+        self.rest().strip_prefix(keyword).is_some_and(|rest| {
+            rest.chars()
+                .next()
+                .is_none_or(|c| c.is_whitespace() || c == '(')
+        })
+    }
+
+    fn term(&mut self) -> anyhow::Result<Term> {
+        if self.peek() == Some('-')
+            && self.rest()[1..]
+                .chars()
+                .next()
+                .is_some_and(|c| !c.is_whitespace())
+        {
+            self.bump();
+            return Ok(Term::Not(Box::new(self.term()?)));
+        }
+
+        self.atom()
+    }
+
+    fn atom(&mut self) -> anyhow::Result<Term> {
+        match self.peek() {
+            Some('(') => {
+                self.bump();
+                let mut terms = self.sequence(true)?;
+                match terms.len() {
+                    0 => anyhow::bail!("empty `()` in search query"),
+                    1 => Ok(terms.remove(0)),
+                    _ => Ok(Term::All(terms)),
+                }
+            }
+            Some('"') => Ok(Term::Phrase(self.quoted()?)),
+            _ => self.word(),
+        }
+    }
+
+    fn quoted(&mut self) -> anyhow::Result<String> {
+        let start = self.position;
+        self.bump();
+
+        let rest = self.rest();
+        let end = rest
+            .find('"')
+            .ok_or_else(|| anyhow::format_err!("unclosed `\"` at position {start}"))?;
+        let value = rest[..end].to_owned();
+        self.position += end + 1;
+
+        Ok(value)
+    }
+
+    fn word(&mut self) -> anyhow::Result<Term> {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_whitespace() && c != '(' && c != ')' && c != '"')
+        {
+            self.bump();
+        }
+        let word = &self.text[start..self.position];
+
+        if let Some(user) = word.strip_prefix('@').filter(|user| !user.is_empty()) {
+            return Ok(Term::Mention(user.to_owned()));
+        }
+
+        let Some((operator, value)) = word.split_once(':') else {
+            return Ok(Term::Word(word.to_owned()));
+        };
+
+        let Ok(operator) = operator.parse::<Operator>() else {
+            return Ok(Term::Word(word.to_owned()));
+        };
+
+        let value = if value.is_empty() && self.peek() == Some('"') {
+            self.quoted()?
+        } else {
+            value.to_owned()
+        };
+
+        if value.is_empty() {
+            anyhow::bail!("`{operator}:` needs a value");
+        }
+
+        operator_term(operator, value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "snake_case")]
+enum Operator {
+    From,
+    To,
+    Since,
+    Until,
+    SinceTime,
+    UntilTime,
+    MinFaves,
+    MinRetweets,
+    MinReplies,
+    Lang,
+    Filter,
+    Url,
+    ConversationId,
+    Near,
+    Within,
+}
+fn operator_term(operator: Operator, value: String) -> anyhow::Result<Term> {
+    let count = |value: &str| {
+        value
+            .parse::<u32>()
+            .map_err(|_| anyhow::format_err!("`{operator}:{value}` expects a number"))
+    };
+    let date = |value: &str| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+            .map_err(|_| anyhow::format_err!("`{operator}:{value}` expects a `YYYY-MM-DD` date"))
+    };
+    let timestamp = |value: &str| {
+        value
+            .parse::<i64>()
+            .ok()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+            .ok_or_else(|| anyhow::format_err!("`{operator}:{value}` expects a unix timestamp"))
+    };
+
+    Ok(match operator {
+        Operator::From => Term::From(value),
+        Operator::To => Term::To(value),
+        Operator::Since => Term::Since(date(&value)?),
+        Operator::Until => Term::Until(date(&value)?),
+        Operator::SinceTime => Term::Since(timestamp(&value)?),
+        Operator::UntilTime => Term::Until(timestamp(&value)?),
+        Operator::MinFaves => Term::MinFaves(count(&value)?),
+        Operator::MinRetweets => Term::MinRetweets(count(&value)?),
+        Operator::MinReplies => Term::MinReplies(count(&value)?),
+        Operator::Lang => Term::Lang(value),
+        Operator::Filter => Term::Filter(
+            value
+                .parse()
+                .map_err(|_| anyhow::format_err!("`filter:{value}` is not a known filter"))?,
+        ),
+        Operator::Url => Term::Url(value),
+        Operator::ConversationId => Term::ConversationId(value),
+        Operator::Near => Term::Near(value),
+        Operator::Within => Term::Within(value),
+    })
+}
+
+fn strip_at(user: &str) -> String {
+    user.strip_prefix('@').unwrap_or(user).to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> DateTime<Utc> {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .unwrap()
+            .and_time(NaiveTime::MIN)
+            .and_utc()
+    }
+
+    #[test]
+    fn builder_renders_search_syntax() {
+        let query = SearchQuery::new()
+            .term(Term::Word("rust".to_owned()))
+            .phrase("memory safety")
+            .any([
+                Term::Word("async".to_owned()),
+                Term::Word("tokio".to_owned()),
+            ])
+            .exclude(Term::Word("crab".to_owned()))
+            .authors(["@rustlang"])
+            .to("ferris")
+            .mention("@rustconf")
+            .since(date("2024-01-01"))
+            .until(DateTime::from_timestamp(1_717_245_296, 0).unwrap())
+            .min_faves(10)
+            .min_retweets(2)
+            .min_replies(1)
+            .lang("en")
+            .filter(Filter::Links)
+            .exclude_filter(Filter::Replies)
+            .url("github.com")
+            .conversation_id("1234567890")
+            .near("San Francisco")
+            .within("15mi");
+
+        assert_eq!(
+            query.to_string(),
+            "rust \"memory safety\" (async OR tokio) -crab from:rustlang to:ferris @rustconf \
+             since:2024-01-01 until_time:1717245296 min_faves:10 min_retweets:2 min_replies:1 \
+             lang:en filter:links -filter:replies url:github.com conversation_id:1234567890 \
+             near:\"San Francisco\" within:15mi"
+        );
+        query.validate().unwrap();
+    }
+
+    #[test]
+    fn normalizes_authors_in_one_place() {
+        assert_eq!(SearchQuery::new().authors(["@a"]).to_string(), "from:a");
+        assert_eq!(
+            SearchQuery::new().authors(["@a", "b", "@c"]).to_string(),
+            "(from:a OR from:b OR from:c)"
+        );
+        assert_eq!(SearchQuery::new().authors([]).to_string(), "");
+        assert_eq!(SearchQuery::new().authors(["@a"]).author(), Some("a"));
+        assert_eq!(SearchQuery::new().authors(["a", "b"]).author(), None);
+    }
+
+    #[test]
+    fn with_range_replaces_date_bounds() {
+        let query = SearchQuery::new()
+            .term(Term::Word("rust".to_owned()))
+            .since(date("2020-01-01"))
+            .until(date("2021-01-01"))
+            .with_range(date("2024-01-01"), date("2024-02-01"));
+
+        assert_eq!(query.to_string(), "rust since:2024-01-01 until:2024-02-01");
+        assert_eq!(query.since_bound(), Some(date("2024-01-01")));
+        assert_eq!(query.until_bound(), Some(date("2024-02-01")));
+    }
+
+    #[test]
+    fn parses_groups_and_negations() {
+        let query = "(rust lang) OR go -(java OR kotlin) -(c sharp)"
+            .parse::<SearchQuery>()
+            .unwrap();
+
+        assert_eq!(
+            query.terms,
+            [
+                Term::Any(vec![
+                    Term::All(vec![
+                        Term::Word("rust".to_owned()),
+                        Term::Word("lang".to_owned())
+                    ]),
+                    Term::Word("go".to_owned()),
+                ]),
+                Term::Not(Box::new(Term::Any(vec![
+                    Term::Word("java".to_owned()),
+                    Term::Word("kotlin".to_owned())
+                ]))),
+                Term::Not(Box::new(Term::All(vec![
+                    Term::Word("c".to_owned()),
+                    Term::Word("sharp".to_owned())
+                ]))),
+            ]
+        );
+        assert_eq!(
+            query.to_string(),
+            "((rust lang) OR go) -(java OR kotlin) -(c sharp)"
+        );
+    }
+
+    #[test]
+    fn display_round_trips_through_from_str() {
+        let queries = [
+            "rust",
+            "\"memory safety\" -crab @rustconf",
+            "(async OR tokio OR smol) lang:en",
+            "((rust lang) OR go) -(java OR kotlin) -(c sharp)",
+            "from:rustlang to:ferris since:2024-01-01 until_time:1717245296",
+            "since_time:1717245296 until:2024-07-01",
+            "min_faves:10 min_retweets:2 min_replies:1",
+            "filter:links -filter:replies url:github.com conversation_id:1234567890",
+            "near:\"San Francisco\" within:15mi near:Berlin within:2.5km",
+            "rust:lang foo:bar",
+        ];
+
+        for text in queries {
+            let query = text.parse::<SearchQuery>().unwrap();
+            assert_eq!(query.to_string(), text);
+            assert_eq!(query.to_string().parse::<SearchQuery>().unwrap(), query);
+        }
+
+        let built = SearchQuery::new()
+            .any([
+                Term::All(vec![
+                    Term::Phrase("type system".to_owned()),
+                    Term::Mention("ferris".to_owned()),
+                ]),
+                Term::Not(Box::new(Term::Filter(Filter::Quote))),
+            ])
+            .authors(["a", "b"]);
+        assert_eq!(built.to_string().parse::<SearchQuery>().unwrap(), built);
+    }
+
+    #[test]
+    fn rejects_malformed_operators() {
+        let cases = [
+            ("from:", "`from:` needs a value"),
+            ("from:not-a-user!", "`not-a-user!` is not a valid username"),
+            ("to:waytoolongusername", "`waytoolongusername` is not a valid username"),
+            ("@bad-user", "`bad-user` is not a valid username"),
+            ("since:2024-13-01", "`since:2024-13-01` expects a `YYYY-MM-DD` date"),
+            ("until:yesterday", "`until:yesterday` expects a `YYYY-MM-DD` date"),
+            ("since_time:soon", "`since_time:soon` expects a unix timestamp"),
+            ("until_time:1.5", "`until_time:1.5` expects a unix timestamp"),
+            ("min_faves:many", "`min_faves:many` expects a number"),
+            ("min_retweets:-1", "`min_retweets:-1` expects a number"),
+            ("min_replies:1e3", "`min_replies:1e3` expects a number"),
+            ("lang:english", "`english` is not a valid language code"),
+            ("filter:gifs", "`filter:gifs` is not a known filter"),
+            ("url:", "`url:` needs a value"),
+            ("conversation_id:12ab", "`12ab` is not a valid conversation id"),
+            ("near:", "`near:` needs a value"),
+            ("within:15", "`15` is not a valid radius, expected e.g. `15mi` or `10km`"),
+            ("within:", "`within:` needs a value"),
+            (
+                "since:2024-02-01 until:2024-01-01",
+                "`since` (2024-02-01 00:00:00 UTC) must be before `until` (2024-01-01 00:00:00 UTC)",
+            ),
+        ];
+
+        for (text, message) in cases {
+            let error = text.parse::<SearchQuery>().unwrap_err();
+            assert_eq!(error.to_string(), message, "{text}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_structure() {
+        let cases = [
+            ("", "the search query is empty"),
+            ("   ", "the search query is empty"),
+            ("(rust", "unclosed `(` in search query"),
+            ("rust)", "unexpected `)` at position 4"),
+            ("()", "empty `()` in search query"),
+            ("OR rust", "`OR` at position 0 has nothing on its left"),
+            (
+                "rust OR OR go",
+                "`OR` at position 8 has nothing on its left",
+            ),
+            (
+                "rust OR",
+                "`OR` at the end of the search query has nothing on its right",
+            ),
+            ("\"rust", "unclosed `\"` at position 0"),
+            ("near:\"San Francisco", "unclosed `\"` at position 5"),
+            ("--rust", "`-rust` cannot be excluded"),
+        ];
+
+        for (text, message) in cases {
+            let error = text.parse::<SearchQuery>().unwrap_err();
+            assert_eq!(error.to_string(), message, "{text}");
+        }
+    }
+}
@@ -1,14 +1,165 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Context as _;
+use futures::FutureExt as _;
+use reqwest::{
+    cookie::{CookieStore as _, Jar},
+    header::HeaderMap,
+};
 use secrecy::{ExposeSecret as _, SecretString};
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
 
-use crate::{auth, config::Config};
+use crate::{
+    auth,
+    config::{AuthConfig, Config},
+    operations::{self, Registry},
+    pagination::{Page, Pagination},
+    transaction::{self, ClientTransaction},
+    types::{
+        profile::{parse_profile, LegacyUserRaw, Profile, TwitterApiErrorRaw},
+        tweets::Tweet,
+    },
+};
 
 pub(crate) struct Scraper {
     client: reqwest::Client,
+    jar: Arc<Jar>,
+    base: reqwest::Url,
     bearer_token: SecretString,
-    csrf: SecretString,
+    csrf: RwLock<SecretString>,
+    session: Option<PathBuf>,
+    operations: Registry,
+    transaction: OnceCell<Option<ClientTransaction>>,
+    headers: HeaderMap,
 }
 
 impl Scraper {
+    fn new(config: Config, session: auth::Session, path: Option<PathBuf>) -> anyhow::Result<Self> {
+        Ok(Self {
+            operations: operations::from_config(&config)?,
+            transaction: OnceCell::new(),
+            headers: config.headers.api_headers()?,
+            client: session.client,
+            jar: session.jar,
+            base: config.base,
+            bearer_token: config.bearer_token,
+            csrf: RwLock::new(session.csrf),
+            session: path,
+        })
+    }
+
+    pub(crate) async fn verify_credentials(&self) -> anyhow::Result<Profile> {
+        let response = self
+            .send(
+                self.client
+                    .get("https://api.twitter.com/1.1/account/verify_credentials.json"),
+            )
+            .await?;
+
+        let user = response.json::<LegacyUserRaw>().await?;
+        let mut profile = parse_profile(&user, None);
+        if profile.id.is_empty() {
+            profile.id = user
+                .id_str
+                .context("the verify_credentials response has no `id_str`")?;
+        }
+
+        Ok(profile)
+    }
+
+    pub(crate) async fn logout(&self) -> anyhow::Result<()> {
+        let response = self
+            .send(
+                self.client
+                    .post("https://api.twitter.com/1.1/account/logout.json"),
+            )
+            .await;
+        if let Err(error) = response {
+            eprintln!("warning: the server did not end the session: {error:#}");
+        }
+
+        if let Some(path) = &self.session {
+            if path.exists() {
+                std::fs::remove_file(path)
+                    .with_context(|| format!("removing `{}`", path.display()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn save_session(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.session else {
+            return Ok(());
+        };
+
+        let cookie = self
+            .jar
+            .cookies(&self.base)
+            .and_then(|cookie| cookie.to_str().ok().map(|cookie| cookie.to_owned().into()))
+            .ok_or_else(|| anyhow::format_err!("no session cookies to save"))?;
+
+        auth::save_session(path, &cookie)
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> anyhow::Result<reqwest::Response> {
+        let mut request = request
+            .headers(self.headers.clone())
+            .bearer_auth(self.bearer_token.expose_secret())
+            .header("x-csrf-token", self.csrf.read().unwrap().expose_secret())
+            .header("x-twitter-auth-type", "OAuth2Session")
+            .build()?;
+
+        if request.url().path().starts_with("/graphql/") {
+            if let Some(transaction) = self.transaction().await {
+                let id = transaction.generate(request.method().as_str(), request.url().path());
+                request
+                    .headers_mut()
+                    .insert("x-client-transaction-id", id.parse()?);
+            }
+        }
+
+        let response = self.client.execute(request).await?;
+        self.sync_csrf(&response);
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        match response.json::<ApiErrors>().await {
+            Ok(ApiErrors { errors }) if !errors.is_empty() => {
+                let messages = errors
+                    .iter()
+                    .map(|error| format!("{} (code {})", error.message, error.code))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                anyhow::bail!("request failed with {status}: {messages}")
+            }
+            _ => anyhow::bail!("request failed with {status}"),
+        }
+    }
+
+    async fn transaction(&self) -> Option<&ClientTransaction> {
+        self.transaction
+            .get_or_init(|| async {
+                transaction::fetch(&self.client)
+                    .await
+                    .inspect_err(|error| {
+                        eprintln!(
+                            "warning: requests will not carry x-client-transaction-id: {error:#}"
+                        );
+                    })
+                    .ok()
+            })
+            .await
+            .as_ref()
+    }
+
     pub(crate) async fn tweets(
         &self,
         search_mode: SearchMode,
@@ -32,6 +183,43 @@ impl Scraper {
             .map(|timeline| crate::types::timeline::search::parse_users(&timeline))
     }
 
+    pub(crate) fn tweets_stream(
+        &self,
+        query: impl Into<String>,
+        search_mode: SearchMode,
+    ) -> Pagination<'_, Tweet> {
+        let query = query.into();
+
+        Pagination::new(move |cursor, count| {
+            let query = query.clone();
+            async move {
+                let response = self.tweets(search_mode, &query, count, cursor).await?;
+                Ok(Page {
+                    items: response.tweets,
+                    next: response.next,
+                })
+            }
+            .boxed()
+        })
+        .chronological(matches!(search_mode, SearchMode::Latest))
+    }
+
+    pub(crate) fn profiles_stream(&self, query: impl Into<String>) -> Pagination<'_, Profile> {
+        let query = query.into();
+
+        Pagination::new(move |cursor, count| {
+            let query = query.clone();
+            async move {
+                let response = self.profiles(&query, count, cursor).await?;
+                Ok(Page {
+                    items: response.profiles,
+                    next: response.next,
+                })
+            }
+            .boxed()
+        })
+    }
+
     async fn tweet_timeline(
         &self,
         search_mode: SearchMode,
@@ -70,67 +258,96 @@ impl Scraper {
             _ => {}
         }
 
-        let features = serde_json::json!({
-            "rweb_lists_timeline_redesign_enabled": true,
-            "responsive_web_graphql_exclude_directive_enabled": true,
-            "verified_phone_label_enabled": false,
-            "creator_subscriptions_tweet_preview_api_enabled": true,
-            "responsive_web_graphql_timeline_navigation_enabled": true,
-            "responsive_web_graphql_skip_user_profile_image_extensions_enabled": false,
-            "tweetypie_unmention_optimization_enabled": true,
-            "responsive_web_edit_tweet_api_enabled": true,
-            "graphql_is_translatable_rweb_tweet_is_translatable_enabled": true,
-            "view_counts_everywhere_api_enabled": true,
-            "longform_notetweets_consumption_enabled": true,
-            "responsive_web_twitter_article_tweet_consumption_enabled": false,
-            "tweet_awards_web_tipping_enabled": false,
-            "freedom_of_speech_not_reach_fetch_enabled": true,
-            "standardized_nudges_misinfo": true,
-            "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled": true,
-            "longform_notetweets_rich_text_read_enabled": true,
-            "longform_notetweets_inline_media_enabled": true,
-            "responsive_web_media_download_video_enabled": false,
-            "responsive_web_enhance_cards_enabled": false,
-        });
+        let request = self.graphql("SearchTimeline", &variables)?;
 
-        let field_toggles = serde_json::json!({
-            "withArticleRichContentState": false
-        });
+        self.send(request)
+            .await?
+            .json::<crate::types::timeline::search::SearchTimeline>()
+            .await
+            .map_err(Into::into)
+    }
+
+    fn graphql(
+        &self,
+        name: &str,
+        variables: &serde_json::Value,
+    ) -> anyhow::Result<reqwest::RequestBuilder> {
+        let operation = self.operations.get(name)?;
 
         let params = &[
-            ("variables", serde_json::to_string(&variables)?),
-            ("features", serde_json::to_string(&features)?),
-            ("fieldToggles", serde_json::to_string(&field_toggles)?),
+            ("variables", serde_json::to_string(variables)?),
+            ("features", serde_json::to_string(&operation.features)?),
+            (
+                "fieldToggles",
+                serde_json::to_string(&operation.field_toggles)?,
+            ),
         ];
 
-        let request = self
+        Ok(self
             .client
-            .get("https://api.twitter.com/graphql/nK1dw4oV3k4w5TdtcAdSww/SearchTimeline")
-            .query(params)
-            .bearer_auth(self.bearer_token.expose_secret())
-            .header("x-csrf-token", self.csrf.expose_secret())
-            .build()?;
+            .get(format!(
+                "https://api.twitter.com/graphql/{}/{name}",
+                operation.query_id
+            ))
+            .query(params))
+    }
 
-        self.client
-            .execute(request)
-            .await?
-            .json::<crate::types::timeline::search::SearchTimeline>()
-            .await
-            .map_err(Into::into)
+    fn sync_csrf(&self, response: &reqwest::Response) {
+        let rotated = response
+            .headers()
+            .get_all(reqwest::header::SET_COOKIE)
+            .iter()
+            .any(|value| value.as_bytes().starts_with(b"ct0="));
+
+        if !rotated {
+            return;
+        }
+
+        if let Some(csrf) = auth::csrf_from_jar(&self.jar, &self.base) {
+            *self.csrf.write().unwrap() = csrf;
+        }
     }
 }
 
 pub(crate) async fn from_config(config: Config) -> anyhow::Result<Scraper> {
-    let (client, csrf) = auth::from_config(&config).await?;
+    let path = config.session_path();
 
-    Ok(Scraper {
-        client,
-        bearer_token: config.bearer_token,
-        csrf,
-    })
+    match auth::load_session(&path)? {
+        Some(cookie) => {
+            let session = auth::login(
+                &config,
+                &AuthConfig::Cookie {
+                    cookie,
+                    proxy: None,
+                },
+            )
+            .await?;
+            Scraper::new(config, session, Some(path))
+        }
+        None => {
+            let session = auth::login(&config, &config.auth).await?;
+            Scraper::new(config, session, None)
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, strum::EnumString, strum::Display)]
+pub(crate) async fn login(config: Config, auth: Option<&AuthConfig>) -> anyhow::Result<Scraper> {
+    let path = config.session_path();
+    let session = auth::login(&config, auth.unwrap_or(&config.auth)).await?;
+
+    Scraper::new(config, session, Some(path))
+}
+
+#[derive(Deserialize)]
+struct ApiErrors {
+    #[serde(default)]
+    errors: Vec<TwitterApiErrorRaw>,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::EnumString, strum::Display,
+)]
+#[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub(crate) enum SearchMode {
     Top,
@@ -139,3 +356,93 @@ pub(crate) enum SearchMode {
     Videos,
     Users,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead as _, BufReader, Write as _},
+        net::TcpListener,
+        sync::Mutex,
+    };
+
+    use super::*;
+
+    fn serve() -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}/", listener.local_addr().unwrap());
+        let tokens = Arc::new(Mutex::new(Vec::new()));
+
+        let log = tokens.clone();
+        std::thread::spawn(move || {
+            for (index, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut token = None;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("x-csrf-token") {
+                            token = Some(value.trim().to_owned());
+                        }
+                    }
+                }
+                log.lock().unwrap().push(token);
+
+                let cookie = match index {
+                    0 => "Set-Cookie: ct0=rotated; Path=/\r\n",
+                    _ => "",
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\n{cookie}Content-Length: 2\r\nConnection: close\r\n\r\n{{}}"
+                )
+                .unwrap();
+            }
+        });
+
+        (base, tokens)
+    }
+
+    #[tokio::test]
+    async fn rotated_csrf_cookie_is_sent_on_the_next_request() {
+        let (base, tokens) = serve();
+        let config: Config = toml::from_str(&format!(
+            r#"
+base = "{base}"
+bearer_token = "token"
+auth = {{ type = "cookie", cookie = "auth_token=1; ct0=original" }}
+"#
+        ))
+        .unwrap();
+
+        let jar = Arc::new(Jar::default());
+        let url = config.base.clone();
+        jar.add_cookie_str("ct0=original", &url);
+        let session = auth::Session {
+            client: reqwest::Client::builder()
+                .cookie_provider(jar.clone())
+                .build()
+                .unwrap(),
+            jar,
+            csrf: "original".into(),
+        };
+        let scraper = Scraper::new(config, session, None).unwrap();
+
+        for _ in 0..2 {
+            scraper
+                .send(scraper.client.get(url.join("account").unwrap()))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            tokens.lock().unwrap().as_slice(),
+            [Some("original".to_owned()), Some("rotated".to_owned())]
+        );
+    }
+}
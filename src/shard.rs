@@ -0,0 +1,201 @@
+use std::{cmp::Reverse, collections::VecDeque};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::{stream::FuturesUnordered, StreamExt as _};
+
+use crate::{
+    pagination::StopReason,
+    query::SearchQuery,
+    scraper::{Scraper, SearchMode},
+    types::{self, tweets::Tweet},
+};
+
+const MIN_WINDOW: TimeDelta = TimeDelta::minutes(10);
+
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+}
+
+impl Window {
+    fn split(self, parts: usize) -> anyhow::Result<Vec<Self>> {
+        let parts = i32::try_from(parts.max(1))
+            .map_err(|_| anyhow::format_err!("cannot split a window into {parts} shards"))?;
+        let step = (self.until - self.since) / parts;
+
+        Ok((0..parts)
+            .map(|part| Self {
+                since: self.since + step * part,
+                until: if part + 1 == parts {
+                    self.until
+                } else {
+                    self.since + step * (part + 1)
+                },
+            })
+            .collect())
+    }
+
+    fn before(self, oldest: DateTime<Utc>) -> Option<Self> {
+        let until = oldest + TimeDelta::seconds(1);
+
+        (until < self.until).then_some(Self {
+            since: self.since,
+            until,
+        })
+    }
+
+    fn query(&self, query: &SearchQuery) -> String {
+        query.clone().with_range(self.since, self.until).to_string()
+    }
+}
+
+pub(crate) struct ShardOptions {
+    pub(crate) shards: usize,
+    pub(crate) concurrency: usize,
+    pub(crate) cap: usize,
+    pub(crate) page_size: u32,
+    pub(crate) max_empty_pages: u32,
+}
+
+pub(crate) struct Sharded {
+    pub(crate) tweets: Vec<Tweet>,
+    pub(crate) failed: usize,
+}
+
+struct Outcome {
+    tweets: Vec<Tweet>,
+    rest: Vec<Window>,
+}
+
+pub(crate) async fn search(
+    scraper: &Scraper,
+    query: &SearchQuery,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    options: &ShardOptions,
+) -> anyhow::Result<Sharded> {
+    if since >= until {
+        anyhow::bail!("`since` ({since}) must be before `until` ({until})");
+    }
+
+    let mut queue = VecDeque::from(Window { since, until }.split(options.shards)?);
+    let mut running = FuturesUnordered::new();
+    let mut tweets = Vec::new();
+    let mut failed = 0;
+
+    loop {
+        while running.len() < options.concurrency.max(1) {
+            let Some(window) = queue.pop_front() else {
+                break;
+            };
+            running.push(async move { (window, crawl(scraper, query, window, options).await) });
+        }
+
+        let Some((window, outcome)) = running.next().await else {
+            break;
+        };
+
+        match outcome {
+            Ok(mut outcome) => {
+                tweets.append(&mut outcome.tweets);
+                queue.extend(outcome.rest);
+            }
+            Err(error) => {
+                failed += 1;
+                eprintln!(
+                    "warning: window {} .. {} failed: {error:#}",
+                    window.since, window.until
+                );
+            }
+        }
+    }
+
+    tweets.sort_by_key(|tweet| Reverse(tweet.time_parsed));
+
+    let mut merged = Vec::with_capacity(tweets.len());
+    types::merge(&mut merged, tweets);
+
+    Ok(Sharded {
+        tweets: merged,
+        failed,
+    })
+}
+
+async fn crawl(
+    scraper: &Scraper,
+    query: &SearchQuery,
+    window: Window,
+    options: &ShardOptions,
+) -> anyhow::Result<Outcome> {
+    let mut stream = scraper
+        .tweets_stream(window.query(query), SearchMode::Latest)
+        .page_size(options.page_size)
+        .max_empty_pages(options.max_empty_pages)
+        .limit(Some(options.cap));
+
+    let mut tweets = Vec::new();
+    while let Some(mut page) = stream.next_page().await? {
+        tweets.append(&mut page);
+    }
+
+    let oldest = tweets.iter().filter_map(|tweet| tweet.time_parsed).min();
+    let rest = match (stream.stop_reason(), oldest) {
+        (Some(StopReason::Limit), Some(oldest)) => match window.before(oldest) {
+            Some(rest) if rest.until - rest.since >= MIN_WINDOW * 2 => rest.split(2)?,
+            Some(rest) => vec![rest],
+            None => {
+                eprintln!(
+                    "warning: window {} .. {} has more than {} tweets in its last second and is cut short",
+                    window.since, window.until, options.cap
+                );
+                Vec::new()
+            }
+        },
+        _ => Vec::new(),
+    };
+
+    Ok(Outcome { tweets, rest })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn splits_into_contiguous_windows() {
+        let window = Window {
+            since: at(0),
+            until: at(100),
+        };
+
+        let parts = window.split(3).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].since, at(0));
+        assert_eq!(parts[2].until, at(100));
+        for pair in parts.windows(2) {
+            assert_eq!(pair[0].until, pair[1].since);
+        }
+
+        let whole = window.split(0).unwrap();
+        assert_eq!((whole[0].since, whole[0].until), (at(0), at(100)));
+
+        assert!(window.split(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn keeps_only_the_uncovered_part_of_a_capped_window() {
+        let window = Window {
+            since: at(0),
+            until: at(100),
+        };
+
+        let rest = window.before(at(40)).unwrap();
+        assert_eq!((rest.since, rest.until), (at(0), at(41)));
+        assert!(window.before(at(99)).is_none());
+    }
+}
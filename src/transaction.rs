@@ -0,0 +1,389 @@
+use std::{
+    sync::LazyLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context as _;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use regex::Regex;
+use sha2::{Digest as _, Sha256};
+
+const KEYWORD: &str = "obfiowerehiring";
+const ADDITIONAL_RANDOM_NUMBER: u8 = 3;
+const EPOCH_OFFSET: u64 = 1_682_924_400;
+const TOTAL_TIME: f64 = 4096.0;
+
+static SITE_VERIFICATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"<meta[^>]*name=["']twitter-site-verification["'][^>]*content=["']([^"']+)["']"#)
+        .unwrap()
+});
+
+static ONDEMAND: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"['"]ondemand\.s['"]:\s*['"](\w*)['"]"#).unwrap());
+
+static INDICES: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\(\w\[(\d{1,2})\],\s*16\)").unwrap());
+
+static FRAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?s)<svg[^>]*id=["']loading-x-anim-\d+["'][^>]*>(.*?)</svg>"#).unwrap()
+});
+
+static PATH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<path[^>]*\sd=["']([^"']+)["']"#).unwrap());
+
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+").unwrap());
+
+pub(crate) struct ClientTransaction {
+    key_bytes: Vec<u8>,
+    animation_key: String,
+}
+
+impl ClientTransaction {
+    pub(crate) fn new(home: &str, ondemand: &str) -> anyhow::Result<Self> {
+        let key = SITE_VERIFICATION
+            .captures(home)
+            .ok_or_else(|| anyhow::format_err!("site verification key not found"))?;
+        let key_bytes = STANDARD
+            .decode(&key[1])
+            .context("decoding site verification key")?;
+
+        let indices = INDICES
+            .captures_iter(ondemand)
+            .map(|captures| captures[1].parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let (&row_index, frame_indices) = indices
+            .split_first()
+            .ok_or_else(|| anyhow::format_err!("key byte indices not found"))?;
+
+        let key_byte = |index: usize| {
+            key_bytes
+                .get(index)
+                .copied()
+                .ok_or_else(|| anyhow::format_err!("key byte index {index} is out of range"))
+        };
+
+        let row_index = usize::from(key_byte(row_index)? % 16);
+        let frame_time = frame_indices.iter().try_fold(1.0, |time, &index| {
+            key_byte(index).map(|byte| time * f64::from(byte % 16))
+        })?;
+        let frame_time = js_round(frame_time / 10.0) * 10.0;
+
+        let frames = frames(home, usize::from(key_byte(5)? % 4))?;
+        let row = frames
+            .get(row_index)
+            .ok_or_else(|| anyhow::format_err!("animation frame row {row_index} not found"))?;
+
+        Ok(Self {
+            animation_key: animate(row, frame_time / TOTAL_TIME)?,
+            key_bytes,
+        })
+    }
+
+    pub(crate) fn generate(&self, method: &str, path: &str) -> String {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            .saturating_sub(EPOCH_OFFSET);
+
+        self.generate_at(method, path, time as u32, rand::random())
+    }
+
+    fn generate_at(&self, method: &str, path: &str, time: u32, random: u8) -> String {
+        let hash = Sha256::digest(format!(
+            "{method}!{path}!{time}{KEYWORD}{}",
+            self.animation_key
+        ));
+
+        let time = time.to_le_bytes();
+        let bytes = self
+            .key_bytes
+            .iter()
+            .chain(&time)
+            .chain(&hash[..16])
+            .chain(&[ADDITIONAL_RANDOM_NUMBER])
+            .map(|byte| byte ^ random);
+
+        let out = std::iter::once(random).chain(bytes).collect::<Vec<_>>();
+
+        STANDARD.encode(out).trim_end_matches('=').to_owned()
+    }
+}
+
+pub(crate) fn ondemand_url(home: &str) -> Option<String> {
+    ONDEMAND.captures(home).map(|captures| {
+        format!(
+            "https://abs.twimg.com/responsive-web/client-web/ondemand.s.{}a.js",
+            &captures[1]
+        )
+    })
+}
+
+pub(crate) async fn fetch(client: &reqwest::Client) -> anyhow::Result<ClientTransaction> {
+    let fetch = |url: String| {
+        let request = client.get(url);
+        async move {
+            request
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await
+                .map_err(anyhow::Error::from)
+        }
+    };
+
+    let home = fetch("https://x.com".to_owned()).await?;
+    let url =
+        ondemand_url(&home).ok_or_else(|| anyhow::format_err!("ondemand.s chunk not found"))?;
+    let ondemand = fetch(url).await?;
+
+    ClientTransaction::new(&home, &ondemand)
+}
+
+fn frames(home: &str, index: usize) -> anyhow::Result<Vec<Vec<u32>>> {
+    let frame = FRAME
+        .captures_iter(home)
+        .nth(index)
+        .ok_or_else(|| anyhow::format_err!("animation frame {index} not found"))?;
+    let path = PATH
+        .captures_iter(&frame[1])
+        .nth(1)
+        .ok_or_else(|| anyhow::format_err!("animation path not found in frame {index}"))?;
+
+    path[1]
+        .get(9..)
+        .unwrap_or_default()
+        .split('C')
+        .map(|segment| {
+            NUMBER
+                .find_iter(segment)
+                .map(|number| number.as_str().parse().map_err(Into::into))
+                .collect()
+        })
+        .collect()
+}
+
+fn animate(frames: &[u32], target_time: f64) -> anyhow::Result<String> {
+    if frames.len() < 11 {
+        anyhow::bail!("animation frame row is too short");
+    }
+
+    let frames = frames
+        .iter()
+        .map(|&value| f64::from(value))
+        .collect::<Vec<_>>();
+
+    let from_color = [frames[0], frames[1], frames[2], 1.0];
+    let to_color = [frames[3], frames[4], frames[5], 1.0];
+    let to_rotation = solve(frames[6], 60.0, 360.0, true);
+
+    let curves = frames[7..]
+        .iter()
+        .enumerate()
+        .map(|(index, &value)| solve(value, if index % 2 == 1 { -1.0 } else { 0.0 }, 1.0, false))
+        .collect::<Vec<_>>();
+
+    let value = cubic(&curves, target_time);
+
+    let color = from_color
+        .iter()
+        .zip(to_color)
+        .map(|(from, to)| interpolate(*from, to, value).max(0.0));
+    let rotation = interpolate(0.0, to_rotation, value).to_radians();
+    let matrix = [
+        rotation.cos(),
+        -rotation.sin(),
+        rotation.sin(),
+        rotation.cos(),
+    ];
+
+    let mut key = color
+        .take(3)
+        .map(|value| format!("{:x}", value.round_ties_even() as i64))
+        .collect::<String>();
+
+    for value in matrix {
+        let hex = float_to_hex(round2(value).abs());
+        if hex.starts_with('.') {
+            key.push('0');
+            key.push_str(&hex.to_lowercase());
+        } else if hex.is_empty() {
+            key.push('0');
+        } else {
+            key.push_str(&hex);
+        }
+    }
+
+    key.push_str("00");
+    key.retain(|char| char != '.' && char != '-');
+
+    Ok(key)
+}
+
+fn solve(value: f64, min: f64, max: f64, rounding: bool) -> f64 {
+    let result = value * (max - min) / 255.0 + min;
+
+    if rounding {
+        result.floor()
+    } else {
+        round2(result)
+    }
+}
+
+fn cubic(curves: &[f64], time: f64) -> f64 {
+    let calculate = |a: f64, b: f64, m: f64| {
+        3.0 * a * (1.0 - m) * (1.0 - m) * m + 3.0 * b * (1.0 - m) * m * m + m * m * m
+    };
+
+    if time <= 0.0 {
+        let gradient = if curves[0] > 0.0 {
+            curves[1] / curves[0]
+        } else if curves[1] == 0.0 && curves[2] > 0.0 {
+            curves[3] / curves[2]
+        } else {
+            0.0
+        };
+
+        return gradient * time;
+    }
+
+    if time >= 1.0 {
+        let gradient = if curves[2] < 1.0 {
+            (curves[3] - 1.0) / (curves[2] - 1.0)
+        } else if curves[2] == 1.0 && curves[0] < 1.0 {
+            (curves[1] - 1.0) / (curves[0] - 1.0)
+        } else {
+            0.0
+        };
+
+        return 1.0 + gradient * (time - 1.0);
+    }
+
+    let (mut start, mut end, mut mid) = (0.0, 1.0, 0.0);
+
+    while start < end {
+        mid = (start + end) / 2.0;
+        let estimate = calculate(curves[0], curves[2], mid);
+
+        if (time - estimate).abs() < 0.00001 {
+            return calculate(curves[1], curves[3], mid);
+        }
+
+        if estimate < time {
+            start = mid;
+        } else {
+            end = mid;
+        }
+    }
+
+    calculate(curves[1], curves[3], mid)
+}
+
+fn interpolate(from: f64, to: f64, value: f64) -> f64 {
+    from * (1.0 - value) + to * value
+}
+
+fn round2(value: f64) -> f64 {
+    format!("{value:.2}").parse().unwrap()
+}
+
+fn js_round(value: f64) -> f64 {
+    let floor = value.floor();
+
+    if value - floor >= 0.5 {
+        value.ceil()
+    } else {
+        floor
+    }
+}
+
+fn float_to_hex(mut value: f64) -> String {
+    let digit = |digit: u32| char::from_digit(digit, 16).unwrap().to_ascii_uppercase();
+
+    let mut quotient = value.trunc();
+    let mut fraction = value - quotient;
+    let mut integer = Vec::new();
+
+    while quotient > 0.0 {
+        quotient = (value / 16.0).trunc();
+        integer.push(digit((value - quotient * 16.0) as u32));
+        value = quotient;
+    }
+
+    let mut result = integer.into_iter().rev().collect::<String>();
+
+    if fraction == 0.0 {
+        return result;
+    }
+
+    result.push('.');
+
+    while fraction > 0.0 {
+        fraction *= 16.0;
+        let integer = fraction.trunc();
+        fraction -= integer;
+        result.push(digit(integer as u32));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The fixtures are synthetic and the expected IDs were produced by this implementation,
+    // so these tests only guard against regressions. They do not show that X accepts the
+    // header; that needs a captured home page, ondemand chunk and a header X accepted.
+    const HOME: &str = include_str!("transaction/fixtures/home.html");
+    const ONDEMAND: &str = include_str!("transaction/fixtures/ondemand.s.js");
+    const SEARCH: &str = "/graphql/nK1dw4oV3k4w5TdtcAdSww/SearchTimeline";
+
+    #[test]
+    fn float_to_hex_matches_reference() {
+        assert_eq!(float_to_hex(0.71), ".B5C28F5C28F5C");
+        assert_eq!(float_to_hex(0.5), ".8");
+        assert_eq!(float_to_hex(255.0), "FF");
+        assert_eq!(float_to_hex(0.26), ".428F5C28F5C29");
+        assert_eq!(float_to_hex(1.0), "1");
+        assert_eq!(float_to_hex(0.0), "");
+        assert_eq!(float_to_hex(12.34), "C.570A3D70A3D7");
+    }
+
+    #[test]
+    fn finds_ondemand_chunk() {
+        assert_eq!(
+            ondemand_url(HOME).as_deref(),
+            Some("https://abs.twimg.com/responsive-web/client-web/ondemand.s.0f7b2c4ea.js")
+        );
+    }
+
+    #[test]
+    fn animation_key_regression() {
+        let transaction = ClientTransaction::new(HOME, ONDEMAND).unwrap();
+
+        assert_eq!(
+            transaction.animation_key,
+            "0d800d70a3d70a3d70808cccccccccccd08cccccccccccd0d70a3d70a3d70800"
+        );
+    }
+
+    #[test]
+    fn transaction_id_regression() {
+        let transaction = ClientTransaction::new(HOME, ONDEMAND).unwrap();
+
+        assert_eq!(
+            transaction.generate_at("GET", SEARCH, 56_000_000, 0),
+            "AKVNyhglMLsdbRMs3tYjey7ZHj9yH8sZcRdElNZJPJ1cNGC+MSAeaf7aoO7ouZl/XAB+VgMd5SWZYnjdjNyz5sv2ZO4dAw"
+        );
+        assert_eq!(
+            transaction.generate_at("GET", SEARCH, 56_000_000, 171),
+            "qw7mYbOOmxC2xriHdX2I0IVytZTZtGCy2rzvP33ilzb3n8sVmou1wlVxC0VDEjLU96vV/ai2To4yydN2J3cYTWBdz0W2qA"
+        );
+        assert_eq!(
+            transaction.generate_at("POST", "/1.1/account/logout.json", 57_654_321, 42),
+            "Ko9n4DIPGpE3RzkG9PwJUQTzNBVYNeEzWz1uvvxjFrd2HkqUGwo0Q9TwisTCk7NVdhuWRSkgJGBOo+1eoIQ/3hlKLfbgKQ"
+        );
+    }
+}
@@ -0,0 +1 @@
+"use strict";(self.webpackChunk_twitter_responsive_web=self.webpackChunk_twitter_responsive_web||[]).push([["ondemand.s"],{20978:(t,e,n)=>{n.d(e,{default:()=>a});const a=(t,e)=>{const n=[];return n.push((t[24], 16)),n.push((t[7], 16)),n.push((t[39], 16)),n.push((t[11], 16)),n.push((t[2], 16)),n}}}]);
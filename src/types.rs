@@ -1,3 +1,92 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
 pub(crate) mod profile;
 pub(crate) mod timeline;
 pub(crate) mod tweets;
+
+pub(crate) trait Entry {
+    fn id(&self) -> Option<&str>;
+
+    fn time(&self) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    fn refresh(&mut self, newer: Self)
+    where
+        Self: Sized;
+}
+
+pub(crate) fn merge<T: Entry>(entries: &mut Vec<T>, other: Vec<T>) -> usize {
+    let mut index = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(position, entry)| Some((entry.id()?.to_owned(), position)))
+        .collect::<HashMap<_, _>>();
+    let mut duplicates = 0;
+
+    for entry in other {
+        match entry.id().and_then(|id| index.get(id)).copied() {
+            Some(position) => {
+                entries[position].refresh(entry);
+                duplicates += 1;
+            }
+            None => {
+                if let Some(id) = entry.id() {
+                    index.insert(id.to_owned(), entries.len());
+                }
+                entries.push(entry);
+            }
+        }
+    }
+
+    duplicates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tweets::Tweet, *};
+
+    fn tweet(id: &str, likes: i32, views: Option<i32>) -> Tweet {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "text": format!("tweet {id}"),
+            "likes": likes,
+            "views": views,
+            "hashtags": [],
+            "urls": [],
+            "mentions": [],
+            "photos": [],
+            "videos": [],
+            "thread": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn merge_keeps_first_seen_order_and_latest_counts() {
+        let mut tweets = vec![tweet("1", 3, Some(100)), tweet("2", 1, None)];
+        let duplicates = merge(
+            &mut tweets,
+            vec![
+                tweet("2", 8, Some(40)),
+                tweet("3", 0, None),
+                tweet("1", 5, None),
+            ],
+        );
+
+        assert_eq!(duplicates, 2);
+        assert_eq!(
+            tweets
+                .iter()
+                .map(|tweet| (tweet.id.as_deref().unwrap(), tweet.likes, tweet.views))
+                .collect::<Vec<_>>(),
+            [
+                ("1", Some(5), Some(100)),
+                ("2", Some(8), Some(40)),
+                ("3", Some(0), None)
+            ]
+        );
+    }
+}
@@ -23,6 +23,19 @@ pub(crate) struct Profile {
     pub(crate) is_blue_verified: Option<bool>,
 }
 
+impl super::Entry for Profile {
+    fn id(&self) -> Option<&str> {
+        Some(self.id.as_str()).filter(|id| !id.is_empty())
+    }
+
+    fn refresh(&mut self, newer: Self) {
+        self.followers_count = newer.followers_count;
+        self.following_count = newer.following_count;
+        self.tweets_count = newer.tweets_count;
+        self.listed_count = newer.listed_count;
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct UserProfile {
     pub(crate) id: String,
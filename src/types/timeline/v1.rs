@@ -41,6 +41,7 @@ pub(crate) struct ExtSensitiveMediaWarningRaw {
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct VideoVariant {
     pub(crate) bitrate: Option<i32>,
+    pub(crate) content_type: Option<String>,
     pub(crate) url: Option<String>,
 }
 
@@ -314,12 +315,6 @@ pub(crate) struct QueryTweetsResponse {
     pub(crate) previous: Option<String>,
 }
 
-impl QueryTweetsResponse {
-    pub(crate) fn merge(&mut self, mut other: Vec<Tweet>) {
-        self.tweets.append(&mut other);
-    }
-}
-
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub(crate) struct QueryProfilesResponse {
     pub(crate) profiles: Vec<Profile>,
@@ -327,12 +322,6 @@ pub(crate) struct QueryProfilesResponse {
     pub(crate) previous: Option<String>,
 }
 
-impl QueryProfilesResponse {
-    pub(crate) fn merge(&mut self, mut other: Vec<Profile>) {
-        self.profiles.append(&mut other);
-    }
-}
-
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct TimelineEntryContent {
     pub(crate) item: Option<TimelineItem>,
@@ -336,7 +336,7 @@ pub(crate) fn parse_media_groups(
                     alt_text: m.ext_alt_text.clone(),
                 });
             }
-            Some("video") => {
+            Some("video" | "animated_gif") => {
                 videos.push(parse_video(m));
             }
             _ => {}
@@ -357,20 +357,25 @@ fn parse_video(m: &TimelineMediaExtendedRaw) -> Video {
         id: m.id_str.clone().unwrap(),
         preview: m.media_url_https.clone().unwrap(),
         url: None,
+        playlist: None,
     };
 
-    let mut max_bitrate = 0;
+    let mut max_bitrate = None;
     if let Some(video_info) = &m.video_info {
         if let Some(variants) = &video_info.variants {
             for variant in variants {
+                if variant.content_type.as_deref() == Some("application/x-mpegURL") {
+                    video.playlist = variant.url.clone();
+                }
+
                 if let (Some(bitrate), Some(url)) = (&variant.bitrate, &variant.url) {
-                    if *bitrate > max_bitrate {
+                    if Some(*bitrate) > max_bitrate {
                         let mut variant_url = url.clone();
                         if let Some(idx) = variant_url.find("?tag=10") {
                             variant_url = variant_url[..idx + 1].to_string();
                         }
                         video.url = Some(variant_url);
-                        max_bitrate = *bitrate;
+                        max_bitrate = Some(*bitrate);
                     }
                 }
             }
@@ -46,6 +46,28 @@ pub(crate) struct Tweet {
     pub(crate) thread_id: Option<String>,
 }
 
+impl super::Entry for Tweet {
+    fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn time(&self) -> Option<DateTime<Utc>> {
+        self.time_parsed
+    }
+
+    fn refresh(&mut self, newer: Self) {
+        self.likes = newer.likes.or(self.likes);
+        self.retweets = newer.retweets.or(self.retweets);
+        self.replies = newer.replies.or(self.replies);
+        self.views = newer.views.or(self.views);
+        self.ext_views = newer.ext_views.or(self.ext_views);
+        self.bookmark_count = newer.bookmark_count.or(self.bookmark_count);
+        self.quote_count = newer.quote_count.or(self.quote_count);
+        self.reply_count = newer.reply_count.or(self.reply_count);
+        self.retweet_count = newer.retweet_count.or(self.retweet_count);
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Mention {
     pub(crate) id: String,
@@ -65,6 +87,7 @@ pub(crate) struct Video {
     pub(crate) id: String,
     pub(crate) preview: String,
     pub(crate) url: Option<String>,
+    pub(crate) playlist: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]